@@ -0,0 +1,295 @@
+//! Minimal `no_std` cron expression support for calendar schedules.
+//!
+//! Expressions use the classic five fields, evaluated in UTC:
+//!
+//! ```text
+//! minute (0-59)  hour (0-23)  day-of-month (1-31)  month (1-12)  day-of-week (0-7)
+//! ```
+//!
+//! Each field accepts `*`, a value `n`, a range `a-b`, a step `*/s`, `a-b/s`
+//! or `n/s`, and comma separated lists of those. Day-of-week `0` and `7` are
+//! both Sunday. As in Vixie cron, when both day-of-month and day-of-week are
+//! restricted a day matches if either of them does; a field starting with
+//! `*`, such as `*/2`, does not count as restricted, so the day must then
+//! match both.
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Longest expression accepted, in bytes.
+pub const MAX_CRON_LEN: usize = 64;
+
+/// How many days ahead [`CronSchedule::next_after`] searches. Eight years
+/// covers expressions that only fire on 29 February.
+const SEARCH_DAYS: u64 = 366 * 8;
+
+/// A parsed cron expression stored as one bitmask per field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parses a five-field cron expression. Returns `None` if it is malformed.
+    pub fn parse(expr: &[u8]) -> Option<CronSchedule> {
+        if expr.len() > MAX_CRON_LEN {
+            return None;
+        }
+
+        let mut fields = expr.split(|b| *b == b' ').filter(|field| !field.is_empty());
+        let minutes = parse_field(fields.next()?, 0, 59)?;
+        let hours = parse_field(fields.next()?, 0, 23)?;
+        let day_field = fields.next()?;
+        let days = parse_field(day_field, 1, 31)?;
+        let months = parse_field(fields.next()?, 1, 12)?;
+        let weekday_field = fields.next()?;
+        let mut weekdays = parse_field(weekday_field, 0, 7)?;
+        if fields.next().is_some() {
+            return None;
+        }
+
+        // Fold Sunday-as-7 onto Sunday-as-0
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Some(CronSchedule {
+            minutes,
+            hours: hours as u32,
+            days: days as u32,
+            months: months as u16,
+            weekdays: weekdays as u8,
+            any_day: day_field.starts_with(b"*"),
+            any_weekday: weekday_field.starts_with(b"*"),
+        })
+    }
+
+    /// Returns the first whole minute strictly after `timestamp` matching the
+    /// expression, or `None` if there is none within the search horizon.
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        let start = (timestamp / SECONDS_PER_MINUTE + 1).checked_mul(SECONDS_PER_MINUTE)?;
+        let first_day = start / SECONDS_PER_DAY;
+        let start_of_day = start % SECONDS_PER_DAY;
+        let start_hour = (start_of_day / 3_600) as u32;
+        let start_minute = ((start_of_day % 3_600) / SECONDS_PER_MINUTE) as u32;
+
+        for day in first_day..first_day + SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+
+            let (min_hour, min_minute) = if day == first_day {
+                (start_hour, start_minute)
+            } else {
+                (0, 0)
+            };
+
+            let mut hours = self.hours & (u32::MAX << min_hour);
+            while hours != 0 {
+                let hour = hours.trailing_zeros();
+                hours &= hours - 1;
+
+                let mut minutes = self.minutes;
+                if hour == min_hour {
+                    minutes &= u64::MAX << min_minute;
+                }
+                if minutes != 0 {
+                    let minute = minutes.trailing_zeros() as u64;
+                    return Some(
+                        day * SECONDS_PER_DAY + hour as u64 * 3_600 + minute * SECONDS_PER_MINUTE,
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, day: u64) -> bool {
+        let (_, month, day_of_month) = civil_from_days(day);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+
+        // 1970-01-01 was a Thursday
+        let weekday = ((day + 4) % 7) as u32;
+        let day_matches = self.days & (1 << day_of_month) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        if self.any_day || self.any_weekday {
+            day_matches && weekday_matches
+        } else {
+            day_matches || weekday_matches
+        }
+    }
+}
+
+/// Parses one comma separated field into a bitmask of allowed values.
+fn parse_field(field: &[u8], min: u32, max: u32) -> Option<u64> {
+    let mut mask = 0u64;
+    for item in field.split(|b| *b == b',') {
+        let (range, step) = match item.iter().position(|b| *b == b'/') {
+            Some(i) => (&item[..i], parse_number(&item[i + 1..])?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return None;
+        }
+
+        let (start, end) = if range == b"*" {
+            (min, max)
+        } else if let Some(i) = range.iter().position(|b| *b == b'-') {
+            (parse_number(&range[..i])?, parse_number(&range[i + 1..])?)
+        } else {
+            let value = parse_number(range)?;
+            // `n/s` runs from n to the end of the field
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+
+        let mut value = start;
+        while value <= end {
+            mask |= 1 << value;
+            value += step;
+        }
+    }
+    Some(mask)
+}
+
+fn parse_number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 2 {
+        return None;
+    }
+    let mut value = 0u32;
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + (digit - b'0') as u32;
+    }
+    Some(value)
+}
+
+/// Converts days since 1970-01-01 into a `(year, month, day)` date in the
+/// proleptic Gregorian calendar (Howard Hinnant's `civil_from_days`).
+fn civil_from_days(days: u64) -> (u64, u32, u32) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a Monday
+    const JAN_1_2024: u64 = 1_704_067_200;
+
+    fn next(expr: &str, timestamp: u64) -> Option<u64> {
+        CronSchedule::parse(expr.as_bytes())
+            .unwrap()
+            .next_after(timestamp)
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_expressions() {
+        for expr in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+            "1,,2 * * * *",
+            "100 * * * *",
+        ] {
+            assert!(CronSchedule::parse(expr.as_bytes()).is_none(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(JAN_1_2024 / SECONDS_PER_DAY), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn test_next_after_every_minute_and_hourly() {
+        assert_eq!(next("* * * * *", JAN_1_2024), Some(JAN_1_2024 + 60));
+        assert_eq!(next("* * * * *", JAN_1_2024 + 59), Some(JAN_1_2024 + 60));
+        assert_eq!(next("0 * * * *", JAN_1_2024), Some(JAN_1_2024 + 3_600));
+        assert_eq!(
+            next("*/15 * * * *", JAN_1_2024 + 1_000),
+            Some(JAN_1_2024 + 1_800)
+        );
+    }
+
+    #[test]
+    fn test_next_after_first_of_month() {
+        // 00:00 UTC on the 1st of every month
+        let feb_1 = JAN_1_2024 + 31 * SECONDS_PER_DAY;
+        assert_eq!(next("0 0 1 * *", JAN_1_2024), Some(feb_1));
+        let mar_1 = feb_1 + 29 * SECONDS_PER_DAY;
+        assert_eq!(next("0 0 1 * *", feb_1), Some(mar_1));
+    }
+
+    #[test]
+    fn test_next_after_weekdays() {
+        // Weekdays at 14:00; 2024-01-05 is a Friday
+        let friday_14h = JAN_1_2024 + 4 * SECONDS_PER_DAY + 14 * 3_600;
+        let monday_14h = friday_14h + 3 * SECONDS_PER_DAY;
+        assert_eq!(next("0 14 * * 1-5", friday_14h), Some(monday_14h));
+        assert_eq!(next("0 14 * * 1-5", friday_14h - 1), Some(friday_14h));
+        // Sunday can be written as 0 or 7
+        let sunday_14h = friday_14h + 2 * SECONDS_PER_DAY;
+        assert_eq!(next("0 14 * * 7", friday_14h), Some(sunday_14h));
+    }
+
+    #[test]
+    fn test_next_after_day_or_weekday() {
+        // The 15th or any Monday, at midnight
+        let jan_8 = JAN_1_2024 + 7 * SECONDS_PER_DAY;
+        let jan_15 = JAN_1_2024 + 14 * SECONDS_PER_DAY;
+        assert_eq!(next("0 0 15 * 1", JAN_1_2024), Some(jan_8));
+        assert_eq!(next("0 0 15 * 1", jan_8), Some(jan_15));
+    }
+
+    #[test]
+    fn test_next_after_starred_step_and_weekday() {
+        // A field starting with `*` is unrestricted, so this is Mondays that
+        // fall on an odd day, not odd days or Mondays
+        let jan_15 = JAN_1_2024 + 14 * SECONDS_PER_DAY;
+        let jan_29 = JAN_1_2024 + 28 * SECONDS_PER_DAY;
+        assert_eq!(next("0 0 */2 * 1", JAN_1_2024), Some(jan_15));
+        assert_eq!(next("0 0 */2 * 1", jan_15), Some(jan_29));
+    }
+
+    #[test]
+    fn test_next_after_impossible_date() {
+        assert_eq!(next("0 0 30 2 *", JAN_1_2024), None);
+        let feb_29_2028 = 1_835_395_200;
+        assert_eq!(
+            next("0 0 29 2 *", JAN_1_2024 + 60 * SECONDS_PER_DAY),
+            Some(feb_29_2028)
+        );
+    }
+}
@@ -1,9 +1,13 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, panic_with_error, Address, Env, IntoVal,
-    Symbol, Val, Vec,
+    contract, contracterror, contractimpl, contracttype, panic_with_error, Address, BytesN, Env,
+    IntoVal, String, Symbol, TryFromVal, Val, Vec,
 };
 
+mod cron;
+
+use cron::{CronSchedule, MAX_CRON_LEN};
+
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
@@ -12,6 +16,94 @@ pub enum Error {
     Unauthorized = 2,
     InsufficientBalance = 3,
     NotInitialized = 4,
+    CancellationPending = 5,
+    TaskPaused = 6,
+    TaskNotFound = 7,
+    BatchTooLarge = 8,
+    InvalidSchedule = 9,
+    TaskCompleted = 10,
+    TargetFailed = 11,
+    HistoryTooLarge = 12,
+    AlreadyInitialized = 13,
+    NoPendingAdmin = 14,
+    InvalidTtlConfig = 15,
+    UnknownTaskFormat = 16,
+    InvalidAmount = 17,
+    Overflow = 18,
+    ArgsTooLarge = 19,
+    WhitelistTooLarge = 20,
+    TaskNotPaused = 21,
+    AllowedFunctionsTooLarge = 22,
+    InvalidTarget = 23,
+}
+
+/// When a task becomes due.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Schedule {
+    /// Every N seconds after the previous run.
+    Interval(u64),
+    /// Five-field cron expression evaluated in UTC, e.g. `"0 14 * * 1-5"`.
+    Cron(String),
+    /// Explicit run times in strictly ascending order, at least one of them
+    /// in the future when the schedule is set.
+    Timestamps(Vec<u64>),
+}
+
+/// The time source a task is scheduled against.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Clock {
+    /// Ledger close time, in seconds.
+    Timestamp,
+    /// Ledger sequence number.
+    LedgerSequence,
+}
+
+/// Retries of a failed run, with exponential backoff. Retry `n` (from 0)
+/// becomes due `base_backoff * multiplier^n` after the failure, on the
+/// task's clock. Once `max_retries` are used up the task waits for its next
+/// regular slot.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Zero disables retries.
+    pub max_retries: u32,
+    pub base_backoff: u64,
+    pub multiplier: u32,
+}
+
+/// What the next due time of a task counts from.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Anchor {
+    /// The time the previous run actually happened, so late runs push
+    /// every later run back.
+    Execution,
+    /// The schedule slot the previous run was for, so late runs do not
+    /// drift. Missed slots are handled by the task's [`CatchUp`] policy.
+    Schedule,
+}
+
+/// How a [`Anchor::Schedule`] task handles slots missed while no keeper ran
+/// it.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CatchUp {
+    /// Run once for the latest missed slot and drop the older ones.
+    SkipToLatest,
+    /// Run the missed slots one after another, keeping at most this many
+    /// behind the latest one.
+    Backfill(u32),
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskStatus {
+    Active,
+    Paused,
+    /// The task reached `end_at` or `max_runs`, or its schedule ran out.
+    Completed,
 }
 
 #[contracttype]
@@ -22,692 +114,5493 @@ pub struct TaskConfig {
     pub function: Symbol,
     pub args: Vec<Val>,
     pub resolver: Option<Address>,
-    pub interval: u64,
+    /// Interface the `resolver` implements.
+    pub resolver_kind: ResolverKind,
+    /// Functions besides `function` that a [`ResolverKind::Plan`] resolver
+    /// may pick.
+    pub allowed_functions: Vec<Symbol>,
+    /// Arguments for the resolver. When `None` it gets the target's `args`.
+    pub resolver_args: Option<Vec<Val>>,
+    /// Whether the resolver takes an [`ExecutionContext`] after its args.
+    pub resolver_context: bool,
+    pub schedule: Schedule,
+    /// Clock that `schedule`, `last_run`, `start_at`, `end_at` and
+    /// `execution_window` are measured in. Cron schedules need
+    /// [`Clock::Timestamp`].
+    pub clock: Clock,
     pub last_run: u64,
     pub gas_balance: i128,
     pub whitelist: Vec<Address>,
+    /// Amount of the gas token paid to the keeper for each successful run.
+    pub reward: i128,
+    /// Seconds that must pass between requesting and finalising a
+    /// cancellation. Zero allows immediate cancellation.
+    pub cancel_notice: u64,
+    /// Timestamp from which a requested cancellation can be finalised.
+    pub cancel_at: Option<u64>,
+    pub status: TaskStatus,
+    /// The task does not run before this timestamp.
+    pub start_at: Option<u64>,
+    /// The task completes once this timestamp has passed.
+    pub end_at: Option<u64>,
+    /// The task completes after this many successful runs.
+    pub max_runs: Option<u32>,
+    /// Number of successful runs so far.
+    pub run_count: u32,
+    pub anchor: Anchor,
+    /// Only used with [`Anchor::Schedule`].
+    pub catch_up: CatchUp,
+    /// Seconds after a slot during which it may still run. A keeper showing
+    /// up later skips the slot instead of invoking the target.
+    pub execution_window: Option<u64>,
+    /// Number of slots skipped because their execution window had closed.
+    pub missed_slots: u32,
+    /// Consecutive failed target calls after which the task is paused.
+    /// `None` never pauses.
+    pub max_failures: Option<u32>,
+    /// Target calls that failed since the last successful run.
+    pub consecutive_failures: u32,
+    /// How failed runs are retried before their slot is given up.
+    pub retry: RetryPolicy,
+    /// Retries made for the current slot.
+    pub retry_count: u32,
+    /// When the next retry is due. Takes precedence over the schedule.
+    pub retry_at: Option<u64>,
+    /// Number of execution records kept, at most [`MAX_HISTORY_SIZE`].
+    /// Zero keeps no history.
+    pub history_size: u32,
 }
 
+/// The fields a creator chooses when registering a task. See
+/// [`TaskConfig`] for what each of them means; the remaining fields there
+/// are bookkeeping kept by the contract.
 #[contracttype]
-pub enum DataKey {
-    Task(u64),
-    Counter,
-    Token,
+#[derive(Clone, Debug)]
+pub struct TaskSpec {
+    pub creator: Address,
+    pub target: Address,
+    pub function: Symbol,
+    pub args: Vec<Val>,
+    pub resolver: Option<Address>,
+    pub resolver_kind: ResolverKind,
+    pub allowed_functions: Vec<Symbol>,
+    pub resolver_args: Option<Vec<Val>>,
+    pub resolver_context: bool,
+    pub schedule: Schedule,
+    pub clock: Clock,
+    pub whitelist: Vec<Address>,
+    pub reward: i128,
+    pub cancel_notice: u64,
+    pub start_at: Option<u64>,
+    pub end_at: Option<u64>,
+    pub max_runs: Option<u32>,
+    pub anchor: Anchor,
+    pub catch_up: CatchUp,
+    pub execution_window: Option<u64>,
+    pub max_failures: Option<u32>,
+    pub retry: RetryPolicy,
+    pub history_size: u32,
 }
 
-pub trait ResolverInterface {
-    fn check_condition(env: Env, args: Vec<Val>) -> bool;
+/// A task entry as written to storage. When [`TaskConfig`] changes shape a
+/// new variant is added, and the previous struct is kept so that existing
+/// entries can be upgraded on read.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum StoredTask {
+    V1(TaskConfig),
 }
 
-#[contract]
-pub struct SoroTaskContract;
-
-#[contractimpl]
-impl SoroTaskContract {
-    /// Registers a new task in the marketplace.
-    /// Returns the unique sequential ID of the registered task.
-    pub fn register(env: Env, config: TaskConfig) -> u64 {
-        // Ensure the creator has authorized the registration
-        config.creator.require_auth();
+/// [`TaskConfig`] as written by the first release, before task entries
+/// were versioned.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TaskConfigV0 {
+    pub creator: Address,
+    pub target: Address,
+    pub function: Symbol,
+    pub args: Vec<Val>,
+    pub resolver: Option<Address>,
+    pub interval: u64,
+    pub last_run: u64,
+    pub gas_balance: i128,
+    pub whitelist: Vec<Address>,
+}
 
-        // Validate the task interval
-        if config.interval == 0 {
-            panic_with_error!(&env, Error::InvalidInterval);
-        }
+/// Which interface a task's resolver implements.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResolverKind {
+    /// [`ResolverInterface`]: a yes/no answer, the target gets the static
+    /// args.
+    Condition,
+    /// [`PlanResolverInterface`]: the resolver returns the call to make.
+    Plan,
+}
 
-        // Generate a unique sequential ID
-        let mut counter: u64 = env
-            .storage()
-            .persistent()
-            .get(&DataKey::Counter)
-            .unwrap_or(0);
-        counter += 1;
-        env.storage().persistent().set(&DataKey::Counter, &counter);
+/// Call computed by a [`ResolverKind::Plan`] resolver.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ExecutionPlan {
+    /// Whether the target should be invoked at all.
+    pub execute: bool,
+    /// Function to call instead of the task's `function`. Must be listed in
+    /// the task's `allowed_functions`.
+    pub function: Option<Symbol>,
+    /// Arguments to pass instead of the task's `args`.
+    pub args: Option<Vec<Val>>,
+}
 
-        // Store the task configuration
-        env.storage()
-            .persistent()
-            .set(&DataKey::Task(counter), &config);
+/// The run a resolver is asked about, passed to resolvers that set
+/// `resolver_context`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionContext {
+    pub task_id: u64,
+    pub last_run: u64,
+    pub run_count: u32,
+    pub keeper: Address,
+    /// Ledger timestamp of the run.
+    pub timestamp: u64,
+}
 
-        // Emit TaskRegistered event
-        env.events().publish(
-            (Symbol::new(&env, "TaskRegistered"), counter),
-            config.creator.clone(),
-        );
+/// Placeholder that `execute` replaces with a value from the
+/// [`ExecutionContext`] when it appears as a top-level target argument.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArgPlaceholder {
+    /// The task ID, as `u64`.
+    TaskId,
+    /// The keeper address.
+    Keeper,
+    /// The ledger timestamp, as `u64`.
+    Timestamp,
+    /// The task's `last_run` before this run, as `u64`.
+    LastRun,
+    /// The number of this run, starting at 1, as `u32`.
+    RunNumber,
+}
 
-        counter
-    }
+/// Replacement value for a task's resolver in a [`TaskUpdate`].
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum ResolverUpdate {
+    Keep,
+    /// Sets a [`ResolverKind::Condition`] resolver.
+    Set(Address),
+    /// Sets a [`ResolverKind::Plan`] resolver.
+    SetPlan(Address),
+    Clear,
+}
 
-    /// Retrieves a task configuration by its ID.
-    pub fn get_task(env: Env, task_id: u64) -> Option<TaskConfig> {
-        env.storage().persistent().get(&DataKey::Task(task_id))
-    }
+/// Replacement value for a task's schedule in a [`TaskUpdate`].
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum ScheduleUpdate {
+    Keep,
+    Set(Schedule),
+}
 
-    pub fn monitor(_env: Env) {
-        // TODO: Implement task monitoring logic
-    }
+/// Partial update applied by [`SoroTaskContract::update_task`].
+/// Fields left as `None` (or `Keep`) keep their current value.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TaskUpdate {
+    pub target: Option<Address>,
+    pub function: Option<Symbol>,
+    pub args: Option<Vec<Val>>,
+    pub resolver: ResolverUpdate,
+    pub schedule: ScheduleUpdate,
+    pub whitelist: Option<Vec<Address>>,
+    pub allowed_functions: Option<Vec<Symbol>>,
+    pub history_size: Option<u32>,
+}
 
-    /// Executes a registered task identified by `task_id`.
-    ///
-    /// # Flow
-    /// 1. Load the [`TaskConfig`] from persistent storage (panics if absent).
-    /// 2. If a `resolver` address is set, call `check_condition(args) -> bool`
-    ///    on it via [`try_invoke_contract`] so that a faulty resolver never
-    ///    permanently blocks execution — a failed call is treated as `false`.
-    /// 3. When the condition is met (or there is no resolver), fire the
-    ///    cross-contract call to `target::function(args)` using
-    ///    [`invoke_contract`].
-    /// 4. Only on a **successful** invocation persist the updated `last_run`
-    ///    timestamp.
-    ///
-    /// # Safety & Atomicity
-    /// Soroban transactions are fully atomic. If the target contract panics the
-    /// entire transaction reverts, so `SoroTask` state is never left in an
-    /// inconsistent half-updated form. `last_run` is written **after** the
-    /// cross-contract call returns, guaranteeing it only reflects completed
-    /// executions.
-    pub fn execute(env: Env, keeper: Address, task_id: u64) {
-        keeper.require_auth();
-        let task_key = DataKey::Task(task_id);
-        let mut config: TaskConfig = env
-            .storage()
-            .persistent()
-            .get(&task_key)
-            .expect("Task not found");
+/// How [`SoroTaskContract::execute_batch`] reacts to a failing task.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BatchMode {
+    /// Record the failure and continue with the remaining tasks.
+    BestEffort,
+    /// Revert the whole batch on the first failure.
+    AllOrNothing,
+}
 
-        if !config.whitelist.is_empty() && !config.whitelist.contains(&keeper) {
-            panic_with_error!(&env, Error::Unauthorized);
-        }
+/// Result of running a single task.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExecutionOutcome {
+    /// The target was invoked and the keeper paid.
+    Executed,
+    /// The task is not due yet; carries the next due time on the task's clock.
+    NotDue(u64),
+    /// The resolver returned `false`.
+    ResolverDenied,
+    /// The resolver call failed or returned something other than a `bool`.
+    ResolverErrored,
+    /// The target call failed with the carried error code (the contract
+    /// error code, or the host error code if the target panicked); its state
+    /// changes were rolled back. The run is retried or its slot given up as
+    /// the task's [`RetryPolicy`] says.
+    TargetFailed(u32),
+    /// The task had reached the end of its lifetime and was marked
+    /// completed instead of running.
+    Completed,
+    /// The execution window had closed; carries the number of slots
+    /// skipped.
+    SlotMissed(u32),
+    /// The task was rejected before running, with the [`Error`] code.
+    /// Only reported by [`SoroTaskContract::execute_batch`].
+    Rejected(u32),
+}
 
-        if env.ledger().timestamp() < config.last_run + config.interval {
-            return;
-        }
+/// Reason code returned by [`SoroTaskContract::can_execute`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExecutionCheck {
+    /// `execute` would invoke the target now.
+    Ready,
+    TaskNotFound,
+    Paused,
+    /// The task has completed, or `execute` would mark it completed.
+    Completed,
+    /// The keeper is not on the task's whitelist.
+    Unauthorized,
+    /// The task is not due yet; carries the next due time on the task's clock.
+    NotDue(u64),
+    /// The execution window of the due slot has closed; carries the slot.
+    /// `execute` would skip it.
+    SlotMissed(u64),
+    /// The `gas_balance` cannot cover the keeper reward.
+    InsufficientBalance,
+    ResolverDenied,
+    ResolverErrored,
+}
 
-        // ── Resolver gate ────────────────────────────────────────────────────
-        // When a resolver is present we use try_invoke_contract so that an
-        // error inside the resolver (panic / wrong return type) degrades
-        // gracefully to "skip this run" rather than aborting the whole tx.
-        //
-        // The resolver's interface is:  check_condition(args: Vec<Val>) -> bool
-        // Its single explicit argument is the task's args vector, so we must
-        // pack config.args into a one-element outer Vec<Val> — otherwise the
-        // host would unpack config.args as individual positional arguments,
-        // causing an argument-count mismatch.
-        let should_execute = match config.resolver {
-            Some(ref resolver_address) => {
-                let mut resolver_call_args = Vec::<Val>::new(&env);
-                resolver_call_args.push_back(config.args.clone().into_val(&env));
-                matches!(
-                    env.try_invoke_contract::<bool, soroban_sdk::Error>(
-                        resolver_address,
-                        &Symbol::new(&env, "check_condition"),
-                        resolver_call_args,
-                    ),
-                    Ok(Ok(true))
-                )
-            }
-            None => true,
-        };
+/// How the contract keeps its storage entries from being archived.
+/// TTLs are counted in ledgers.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfig {
+    /// Entries whose TTL dropped below this are extended.
+    pub threshold: u32,
+    /// TTL that entries are extended to.
+    pub extend_to: u32,
+    /// Gas token charged to a task's `gas_balance` on each successful run
+    /// and paid to the keeper on top of the reward, covering the rent the
+    /// keeper pays for extending the task's entries. Zero disables it.
+    pub rent_fee: i128,
+}
 
-        if should_execute {
-            // ── Cross-contract call ──────────────────────────────────────────
-            // `args` is Vec<Val> as stored in TaskConfig — passed directly.
-            // The return value is discarded; callers can read target state
-            // independently if needed.
-            env.invoke_contract::<Val>(&config.target, &config.function, config.args.clone());
+/// One entry of a task's execution history.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionRecord {
+    /// Ledger timestamp of the attempt.
+    pub timestamp: u64,
+    pub keeper: Address,
+    pub outcome: ExecutionOutcome,
+    /// Reward paid to the keeper for this attempt.
+    pub reward: i128,
+}
 
-            // ── State update ────────────────────────────────────────────────
-            // Reached only when invoke_contract returned without panic.
-            // Record the ledger timestamp of this successful execution.
-            config.last_run = env.ledger().timestamp();
-            env.storage().persistent().set(&task_key, &config);
-        }
-    }
+/// A task that is ready to run, as reported by
+/// [`SoroTaskContract::get_due_tasks`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DueTask {
+    pub task_id: u64,
+    pub reward: i128,
+}
 
-    /// Initializes the contract with a gas token.
-    pub fn init(env: Env, token: Address) {
-        if env.storage().instance().has(&DataKey::Token) {
-            panic!("Already initialized");
-        }
-        env.storage().instance().set(&DataKey::Token, &token);
-    }
+/// One page of [`SoroTaskContract::list_tasks`].
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TaskPage {
+    pub tasks: Vec<(u64, TaskConfig)>,
+    /// `start_after` for the next page, or `None` once every ID was probed.
+    pub next: Option<u64>,
+}
 
-    /// Deposits gas tokens to a task's balance.
-    pub fn deposit_gas(env: Env, task_id: u64, from: Address, amount: i128) {
-        from.require_auth();
+/// Position in the due queue where a [`SoroTaskContract::get_due_tasks`]
+/// scan starts or stopped.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DueCursor {
+    /// The beginning of the queue.
+    Start,
+    /// Right after a task in a due bucket of a clock: `(clock, bucket,
+    /// task_id)`.
+    After(Clock, u64, u64),
+    /// The scan reached the end of the queue.
+    End,
+}
 
-        let task_key = DataKey::Task(task_id);
-        let mut config: TaskConfig = env
-            .storage()
-            .persistent()
-            .get(&task_key)
-            .expect("Task not found");
+/// One page of [`SoroTaskContract::get_due_tasks`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuePage {
+    pub tasks: Vec<DueTask>,
+    /// Where to resume the scan, or [`DueCursor::End`] once it is complete.
+    pub next: DueCursor,
+}
 
-        let token_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::Token)
-            .expect("Not initialized");
+#[contracttype]
+pub enum DataKey {
+    Task(u64),
+    Counter,
+    Token,
+    CreatorTasks(Address),
+    /// Sorted IDs of the bucket ranges of a clock that hold queued tasks.
+    DueRanges(Clock),
+    /// Sorted IDs of the non-empty due buckets within a bucket range.
+    DueBuckets(Clock, u64),
+    /// Sorted IDs of the non-empty pages of a due bucket.
+    DuePages(Clock, u64),
+    /// Sorted IDs of the tasks in one page of a due bucket, whose next due
+    /// time falls inside the bucket.
+    DueBucket(Clock, u64, u64),
+    /// Latest execution records of a task, oldest first.
+    History(u64),
+    /// The [`TtlConfig`], kept in instance storage.
+    TtlConfig,
+    /// Address allowed to govern the contract.
+    Admin,
+    /// Admin proposed by the current one, until it accepts.
+    PendingAdmin,
+}
 
-        // Transfer tokens to contract
-        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
-        token_client.transfer(&from, &env.current_contract_address(), &amount);
+/// Version reported by [`SoroTaskContract::version`]. Bump it with every
+/// release.
+pub const CONTRACT_VERSION: u32 = 1;
 
-        // Update balance
-        config.gas_balance += amount;
-        env.storage().persistent().set(&task_key, &config);
+/// Upper bound on the number of tasks returned by a single page query.
+pub const MAX_PAGE_SIZE: u32 = 50;
 
-        // Emit event
-        env.events()
-            .publish((Symbol::new(&env, "GasDeposited"), task_id), (from, amount));
-    }
+/// Upper bound on the task IDs a single `list_tasks` call probes, whether
+/// or not they still hold a task.
+pub const MAX_LIST_SCAN: u64 = 100;
 
-    /// Withdraws gas tokens from a task's balance.
-    /// Only the task creator can withdraw.
-    pub fn withdraw_gas(env: Env, task_id: u64, amount: i128) {
-        let task_key = DataKey::Task(task_id);
-        let mut config: TaskConfig = env
-            .storage()
-            .persistent()
-            .get(&task_key)
-            .expect("Task not found");
+/// Upper bound on the number of tasks in one `execute_batch` call.
+pub const MAX_BATCH_SIZE: u32 = 20;
 
-        // Ensure only creator can withdraw
-        config.creator.require_auth();
+/// Default [`TtlConfig::threshold`], about a week of ledgers.
+pub const DEFAULT_TTL_THRESHOLD: u32 = 120_960;
 
-        if config.gas_balance < amount {
-            panic_with_error!(&env, Error::InsufficientBalance);
-        }
+/// Default [`TtlConfig::extend_to`], about a month of ledgers.
+pub const DEFAULT_TTL_EXTEND_TO: u32 = 518_400;
 
-        let token_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::Token)
-            .expect("Not initialized");
+/// Upper bound on the execution records kept per task.
+pub const MAX_HISTORY_SIZE: u32 = 50;
 
-        // Transfer tokens back to creator
-        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
-        token_client.transfer(&env.current_contract_address(), &config.creator, &amount);
+/// Upper bound on the arguments passed to a target or resolver.
+pub const MAX_ARGS: u32 = 16;
 
-        // Update balance
-        config.gas_balance -= amount;
-        env.storage().persistent().set(&task_key, &config);
+/// Upper bound on the keepers in a task's whitelist.
+pub const MAX_WHITELIST_SIZE: u32 = 20;
 
-        // Emit event
-        env.events().publish(
-            (Symbol::new(&env, "GasWithdrawn"), task_id),
-            (config.creator.clone(), amount),
-        );
-    }
+/// Upper bound on the functions a plan resolver may pick from.
+pub const MAX_ALLOWED_FUNCTIONS: u32 = 16;
 
-    /// Returns the global gas token address.
-    pub fn get_token(env: Env) -> Address {
-        env.storage()
-            .instance()
-            .get(&DataKey::Token)
-            .expect("Not initialized")
-    }
-}
+/// Upper bound on a task's `reward`. Together with [`MAX_RENT_FEE`] it keeps
+/// the payout of a run from overflowing.
+pub const MAX_REWARD: i128 = i128::MAX / 2;
 
-// ============================================================================
-// Tests
-// ============================================================================
+/// Upper bound on [`TtlConfig::rent_fee`].
+pub const MAX_RENT_FEE: i128 = i128::MAX / 2;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use soroban_sdk::{
-        contract, contractimpl,
-        testutils::{Address as _, Events, Ledger as _},
-        vec, Env, FromVal, IntoVal,
-    };
+/// Upper bound on the number of run times in a [`Schedule::Timestamps`].
+pub const MAX_SCHEDULE_TIMESTAMPS: u32 = 32;
 
-    // ── Mock Contracts ───────────────────────────────────────────────────────
+/// Upper bound on [`CatchUp::Backfill`].
+pub const MAX_BACKFILL_RUNS: u32 = 100;
 
-    #[contract]
-    pub struct DummyContract;
+/// Upper bound on the calendar slots inspected when catching up a late
+/// task. Older missed slots beyond it are dropped.
+const MAX_CATCH_UP_SCAN: u32 = 1_000;
 
-    #[contractimpl]
-    impl DummyContract {
-        pub fn hello(_env: Env) {}
-    }
+/// Width in seconds of a bucket in the timestamp due queue.
+pub const BUCKET_SECONDS: u64 = 300;
 
-    /// Minimal target contract with two callable functions.
-    #[contract]
-    pub struct MockTarget;
+/// Width in ledgers of a bucket in the ledger-sequence due queue.
+pub const BUCKET_LEDGERS: u64 = 60;
 
-    #[contractimpl]
-    impl MockTarget {
-        /// Zero-argument smoke-test function.
-        pub fn ping(_env: Env) -> bool {
-            true
-        }
+/// Number of consecutive due buckets indexed under one key, a day of
+/// timestamp buckets.
+pub const BUCKETS_PER_RANGE: u64 = 288;
 
-        /// Two-argument function — verifies args are forwarded correctly.
-        pub fn add(_env: Env, a: i64, b: i64) -> i64 {
-            a + b
-        }
-    }
+/// Span of task IDs sharing one page of a due bucket. Caps the entries
+/// written when a task enters or leaves a crowded bucket.
+pub const DUE_PAGE_SIZE: u64 = 128;
 
-    // ── Resolver contracts (separate sub-modules) ───────────────────────
+/// Upper bound on the queued tasks a single `get_due_tasks` call examines,
+/// whether or not they turn out to be due.
+pub const MAX_DUE_SCAN: u32 = 100;
 
-    /// Resolver that always approves execution.
-    mod resolver_true {
-        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+pub trait ResolverInterface {
+    fn check_condition(env: Env, args: Vec<Val>) -> bool;
+}
 
-        #[contract]
-        pub struct MockResolverTrue;
+/// Resolver that decides how the target is called, Gelato style.
+pub trait PlanResolverInterface {
+    fn plan_execution(env: Env, args: Vec<Val>) -> ExecutionPlan;
+}
 
-        #[contractimpl]
-        impl MockResolverTrue {
-            pub fn check_condition(_env: Env, _args: Vec<Val>) -> bool {
-                true
+/// Returns `args` with every [`ArgPlaceholder`] replaced by its value in
+/// `context`.
+fn fill_placeholders(env: &Env, args: Vec<Val>, context: &ExecutionContext) -> Vec<Val> {
+    let mut filled = Vec::new(env);
+    for arg in args.iter() {
+        let value = match ArgPlaceholder::try_from_val(env, &arg) {
+            Ok(ArgPlaceholder::TaskId) => context.task_id.into_val(env),
+            Ok(ArgPlaceholder::Keeper) => context.keeper.into_val(env),
+            Ok(ArgPlaceholder::Timestamp) => context.timestamp.into_val(env),
+            Ok(ArgPlaceholder::LastRun) => context.last_run.into_val(env),
+            Ok(ArgPlaceholder::RunNumber) => {
+                checked(env, context.run_count.checked_add(1)).into_val(env)
             }
-        }
+            Err(_) => arg,
+        };
+        filled.push_back(value);
     }
+    filled
+}
 
-    /// Resolver that always denies execution.
-    mod resolver_false {
-        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+/// Parses a cron schedule stored as a contract string.
+fn parse_cron(expr: &String) -> Option<CronSchedule> {
+    let len = expr.len() as usize;
+    if len > MAX_CRON_LEN {
+        return None;
+    }
+    let mut buf = [0u8; MAX_CRON_LEN];
+    expr.copy_into_slice(&mut buf[..len]);
+    CronSchedule::parse(&buf[..len])
+}
 
-        #[contract]
-        pub struct MockResolverFalse;
+/// Returns the earliest time at which the task may run again, a pending
+/// retry included, or `None` when it has no further runs within its
+/// lifetime.
+fn next_due(config: &TaskConfig) -> Option<u64> {
+    match config.retry_at {
+        Some(retry_at) => Some(retry_at),
+        None => next_slot(config),
+    }
+}
 
-        #[contractimpl]
-        impl MockResolverFalse {
-            pub fn check_condition(_env: Env, _args: Vec<Val>) -> bool {
-                false
-            }
-        }
+/// Returns the next slot of the task's regular schedule.
+fn next_slot(config: &TaskConfig) -> Option<u64> {
+    if config
+        .max_runs
+        .is_some_and(|max_runs| config.run_count >= max_runs)
+    {
+        return None;
     }
 
-    // ── Helpers ──────────────────────────────────────────────────────────────
+    // Calendar slots before `start_at` are skipped rather than delayed
+    let start_at = config.start_at.unwrap_or(0);
+    let after = config.last_run.max(start_at.saturating_sub(1));
+    let due = match &config.schedule {
+        Schedule::Interval(interval) => config.last_run.checked_add(*interval)?.max(start_at),
+        Schedule::Cron(expr) => parse_cron(expr)?.next_after(after)?,
+        Schedule::Timestamps(timestamps) => timestamps.iter().find(|t| *t > after)?,
+    };
 
-    fn setup() -> (Env, Address) {
+    match config.end_at {
+        Some(end_at) if due > end_at => None,
+        _ => Some(due),
+    }
+}
+
+/// Returns the schedule slot a run at `now` counts for when the task is
+/// anchored to its schedule. `due` is the earliest slot not yet run; an
+/// early run, such as a retry, counts for `due` itself.
+fn anchored_slot(config: &TaskConfig, due: u64, now: u64) -> u64 {
+    if due > now {
+        return due;
+    }
+    let keep = match config.catch_up {
+        CatchUp::SkipToLatest => 0,
+        CatchUp::Backfill(runs) => runs as u64,
+    };
+
+    match &config.schedule {
+        Schedule::Interval(interval) => {
+            let missed = now.saturating_sub(due) / interval;
+            due.saturating_add(missed.saturating_sub(keep).saturating_mul(*interval))
+        }
+        Schedule::Cron(_) | Schedule::Timestamps(_) => {
+            // Walk the slots up to `now`, remembering the last `keep + 1` of them
+            let mut window = [0u64; MAX_BACKFILL_RUNS as usize + 1];
+            let size = keep as usize + 1;
+            let mut seen = 0usize;
+            let mut slot = Some(due);
+            let mut probe = config.clone();
+            while let Some(current) = slot.filter(|s| *s <= now) {
+                window[seen % size] = current;
+                seen += 1;
+                if seen as u32 >= MAX_CATCH_UP_SCAN {
+                    break;
+                }
+                probe.last_run = current;
+                slot = next_slot(&probe);
+            }
+            if seen <= size {
+                due
+            } else {
+                window[seen % size]
+            }
+        }
+    }
+}
+
+/// Skips every slot from `due` on whose execution window closed before
+/// `now`, leaving `last_run` on the last of them. Returns how many were
+/// skipped.
+fn skip_missed_slots(config: &mut TaskConfig, due: u64, now: u64, window: u64) -> u32 {
+    match config.schedule {
+        Schedule::Interval(interval) => {
+            let late = now.saturating_sub(window).saturating_sub(due);
+            let missed = late.div_ceil(interval);
+            config.last_run = due.saturating_add(missed.saturating_sub(1).saturating_mul(interval));
+            missed.min(u32::MAX as u64) as u32
+        }
+        Schedule::Cron(_) | Schedule::Timestamps(_) => {
+            let mut missed = 0u32;
+            let mut slot = Some(due);
+            while let Some(current) = slot.filter(|s| s.saturating_add(window) < now) {
+                config.last_run = current;
+                missed += 1;
+                if missed >= MAX_CATCH_UP_SCAN {
+                    break;
+                }
+                slot = next_slot(config);
+            }
+            missed
+        }
+    }
+}
+
+/// Reads a task, upgrading entries written by older releases.
+fn load_task(env: &Env, task_id: u64) -> Option<TaskConfig> {
+    let stored: Val = env.storage().persistent().get(&DataKey::Task(task_id))?;
+    if let Ok(StoredTask::V1(config)) = StoredTask::try_from_val(env, &stored) {
+        return Some(config);
+    }
+    match TaskConfigV0::try_from_val(env, &stored) {
+        Ok(legacy) => Some(upgrade_v0(env, legacy)),
+        Err(_) => panic_with_error!(env, Error::UnknownTaskFormat),
+    }
+}
+
+/// Writes a task in the current storage format.
+fn save_task(env: &Env, task_id: u64, config: &TaskConfig) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::Task(task_id), &StoredTask::V1(config.clone()));
+}
+
+/// Builds the state of a newly registered task. The schedule counts from
+/// the registration time and the gas balance starts empty.
+fn new_task(env: &Env, spec: TaskSpec) -> TaskConfig {
+    TaskConfig {
+        last_run: clock_now(env, spec.clock),
+        creator: spec.creator,
+        target: spec.target,
+        function: spec.function,
+        args: spec.args,
+        resolver: spec.resolver,
+        resolver_kind: spec.resolver_kind,
+        allowed_functions: spec.allowed_functions,
+        resolver_args: spec.resolver_args,
+        resolver_context: spec.resolver_context,
+        schedule: spec.schedule,
+        clock: spec.clock,
+        gas_balance: 0,
+        whitelist: spec.whitelist,
+        reward: spec.reward,
+        cancel_notice: spec.cancel_notice,
+        cancel_at: None,
+        status: TaskStatus::Active,
+        start_at: spec.start_at,
+        end_at: spec.end_at,
+        max_runs: spec.max_runs,
+        run_count: 0,
+        anchor: spec.anchor,
+        catch_up: spec.catch_up,
+        execution_window: spec.execution_window,
+        missed_slots: 0,
+        max_failures: spec.max_failures,
+        consecutive_failures: 0,
+        retry: spec.retry,
+        retry_count: 0,
+        retry_at: None,
+        history_size: spec.history_size,
+    }
+}
+
+/// Fills the fields added since the first release with the values that
+/// keep a task behaving as it did.
+fn upgrade_v0(env: &Env, legacy: TaskConfigV0) -> TaskConfig {
+    TaskConfig {
+        creator: legacy.creator,
+        target: legacy.target,
+        function: legacy.function,
+        args: legacy.args,
+        resolver: legacy.resolver,
+        resolver_kind: ResolverKind::Condition,
+        allowed_functions: Vec::new(env),
+        resolver_args: None,
+        resolver_context: false,
+        schedule: Schedule::Interval(legacy.interval),
+        clock: Clock::Timestamp,
+        last_run: legacy.last_run,
+        gas_balance: legacy.gas_balance,
+        whitelist: legacy.whitelist,
+        reward: 0,
+        cancel_notice: 0,
+        cancel_at: None,
+        status: TaskStatus::Active,
+        start_at: None,
+        end_at: None,
+        max_runs: None,
+        run_count: 0,
+        anchor: Anchor::Execution,
+        catch_up: CatchUp::SkipToLatest,
+        execution_window: None,
+        missed_slots: 0,
+        max_failures: None,
+        consecutive_failures: 0,
+        retry: RetryPolicy {
+            max_retries: 0,
+            base_backoff: 0,
+            multiplier: 0,
+        },
+        retry_count: 0,
+        retry_at: None,
+        history_size: 0,
+    }
+}
+
+/// Loads a task, raising [`Error::TaskNotFound`] if it does not exist.
+fn require_task(env: &Env, task_id: u64) -> TaskConfig {
+    load_task(env, task_id).unwrap_or_else(|| panic_with_error!(env, Error::TaskNotFound))
+}
+
+/// Returns a client for the gas token set at `init`.
+fn gas_token(env: &Env) -> soroban_sdk::token::Client<'_> {
+    let token_address: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Token)
+        .unwrap_or_else(|| panic_with_error!(env, Error::NotInitialized));
+    soroban_sdk::token::Client::new(env, &token_address)
+}
+
+/// Unwraps the result of checked arithmetic, raising [`Error::Overflow`].
+fn checked<T>(env: &Env, value: Option<T>) -> T {
+    value.unwrap_or_else(|| panic_with_error!(env, Error::Overflow))
+}
+
+/// Amount taken from the escrow for each successful run: the keeper
+/// reward plus the rent fee. Saturates rather than failing, so a task
+/// stored before rewards were capped reads as unfunded instead of breaking
+/// the views that list it.
+fn run_payout(env: &Env, config: &TaskConfig) -> i128 {
+    config.reward.saturating_add(ttl_config(env).rent_fee)
+}
+
+/// Loads the admin and requires its authorization.
+fn require_admin(env: &Env) -> Address {
+    let admin: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .unwrap_or_else(|| panic_with_error!(env, Error::NotInitialized));
+    admin.require_auth();
+    admin
+}
+
+/// Returns the TTL configuration, falling back to the defaults.
+fn ttl_config(env: &Env) -> TtlConfig {
+    env.storage()
+        .instance()
+        .get(&DataKey::TtlConfig)
+        .unwrap_or(TtlConfig {
+            threshold: DEFAULT_TTL_THRESHOLD,
+            extend_to: DEFAULT_TTL_EXTEND_TO,
+            rent_fee: 0,
+        })
+}
+
+/// Extends the TTL of every entry a task needs to run, and of the instance
+/// storage holding the token, so that rarely executed tasks are not
+/// archived.
+fn extend_task_ttl(env: &Env, task_id: u64, config: &TaskConfig) {
+    let ttl = ttl_config(env);
+    let storage = env.storage().persistent();
+    storage.extend_ttl(&DataKey::Task(task_id), ttl.threshold, ttl.extend_to);
+
+    let extend_if_present = |key: DataKey| {
+        if storage.has(&key) {
+            storage.extend_ttl(&key, ttl.threshold, ttl.extend_to);
+        }
+    };
+    extend_if_present(DataKey::History(task_id));
+    extend_if_present(DataKey::CreatorTasks(config.creator.clone()));
+    if let Some((clock, bucket)) = due_bucket(config) {
+        extend_if_present(DataKey::DueRanges(clock));
+        extend_if_present(DataKey::DueBuckets(clock, bucket / BUCKETS_PER_RANGE));
+        extend_if_present(DataKey::DuePages(clock, bucket));
+        extend_if_present(DataKey::DueBucket(clock, bucket, task_id / DUE_PAGE_SIZE));
+    }
+
+    env.storage()
+        .instance()
+        .extend_ttl(ttl.threshold, ttl.extend_to);
+}
+
+/// Returns the current reading of `clock`.
+fn clock_now(env: &Env, clock: Clock) -> u64 {
+    match clock {
+        Clock::Timestamp => env.ledger().timestamp(),
+        Clock::LedgerSequence => env.ledger().sequence() as u64,
+    }
+}
+
+fn bucket_width(clock: Clock) -> u64 {
+    match clock {
+        Clock::Timestamp => BUCKET_SECONDS,
+        Clock::LedgerSequence => BUCKET_LEDGERS,
+    }
+}
+
+/// Whether an active task has reached the end of its lifetime.
+fn is_finished(env: &Env, config: &TaskConfig) -> bool {
+    config
+        .end_at
+        .is_some_and(|end_at| clock_now(env, config.clock) > end_at)
+        || next_due(config).is_none()
+}
+
+/// Marks a task completed. Any leftover `gas_balance` stays withdrawable by
+/// the creator.
+fn complete_task(env: &Env, task_id: u64, config: &mut TaskConfig) {
+    config.status = TaskStatus::Completed;
+    env.events().publish(
+        (Symbol::new(env, "TaskCompleted"), task_id),
+        (config.run_count, config.gas_balance),
+    );
+}
+
+/// Returns the due bucket of an active task, or `None` when it is not
+/// scheduled.
+fn due_bucket(config: &TaskConfig) -> Option<(Clock, u64)> {
+    let clock = config.clock;
+    match config.status {
+        TaskStatus::Active => next_due(config).map(|due| (clock, due / bucket_width(clock))),
+        TaskStatus::Paused | TaskStatus::Completed => None,
+    }
+}
+
+/// Returns the due bucket a task is queued in. Tasks whose balance cannot
+/// pay for a run stay out of the queue until they are funded, so they cost
+/// `get_due_tasks` nothing.
+fn queue_bucket(env: &Env, config: &TaskConfig) -> Option<(Clock, u64)> {
+    due_bucket(config).filter(|_| config.gas_balance >= run_payout(env, config))
+}
+
+/// Inserts `value` into the sorted set stored under `key`. Returns whether
+/// the set was created by this insert.
+fn set_insert(env: &Env, key: &DataKey, value: u64) -> bool {
+    let stored: Option<Vec<u64>> = env.storage().persistent().get(key);
+    let created = stored.is_none();
+    let mut values = stored.unwrap_or(Vec::new(env));
+    if let Err(i) = values.binary_search(value) {
+        values.insert(i, value);
+        env.storage().persistent().set(key, &values);
+    }
+    created
+}
+
+/// Removes `value` from the sorted set stored under `key`, deleting the
+/// entry once it is empty. Returns whether it was deleted.
+fn set_remove(env: &Env, key: &DataKey, value: u64) -> bool {
+    let Some(mut values) = env.storage().persistent().get::<_, Vec<u64>>(key) else {
+        return false;
+    };
+    let Ok(i) = values.binary_search(value) else {
+        return false;
+    };
+    values.remove(i);
+    if values.is_empty() {
+        env.storage().persistent().remove(key);
+        true
+    } else {
+        env.storage().persistent().set(key, &values);
+        false
+    }
+}
+
+/// Adds a task to a due bucket. Every level of the queue is written only
+/// when the level below it gained its first entry, so the cost does not
+/// grow with the number of queued tasks. Adding a queued task again
+/// changes nothing.
+fn enqueue(env: &Env, task_id: u64, (clock, bucket): (Clock, u64)) {
+    let range = bucket / BUCKETS_PER_RANGE;
+    let page = task_id / DUE_PAGE_SIZE;
+    if set_insert(env, &DataKey::DueBucket(clock, bucket, page), task_id)
+        && set_insert(env, &DataKey::DuePages(clock, bucket), page)
+        && set_insert(env, &DataKey::DueBuckets(clock, range), bucket)
+    {
+        set_insert(env, &DataKey::DueRanges(clock), range);
+    }
+}
+
+/// Removes a task from a due bucket, dropping every level of the queue
+/// that becomes empty.
+fn dequeue(env: &Env, task_id: u64, (clock, bucket): (Clock, u64)) {
+    let range = bucket / BUCKETS_PER_RANGE;
+    let page = task_id / DUE_PAGE_SIZE;
+    if set_remove(env, &DataKey::DueBucket(clock, bucket, page), task_id)
+        && set_remove(env, &DataKey::DuePages(clock, bucket), page)
+        && set_remove(env, &DataKey::DueBuckets(clock, range), bucket)
+    {
+        set_remove(env, &DataKey::DueRanges(clock), range);
+    }
+}
+
+/// Keeps the due queue in sync after a task's schedule, status or balance
+/// changed.
+fn reschedule(env: &Env, task_id: u64, before: &TaskConfig, after: &TaskConfig) {
+    let new_bucket = queue_bucket(env, after);
+    if new_bucket.is_some() && queue_bucket(env, before) == new_bucket {
+        return;
+    }
+    // A rent fee raised since `before` was queued hides it from
+    // `queue_bucket`, so look for it wherever it is due
+    if let Some(bucket) = due_bucket(before).filter(|bucket| Some(*bucket) != new_bucket) {
+        dequeue(env, task_id, bucket);
+    }
+    if let Some(bucket) = new_bucket {
+        enqueue(env, task_id, bucket);
+    }
+}
+
+/// Checks the user-supplied parts of a task configuration.
+fn validate_config(env: &Env, config: &TaskConfig) {
+    match &config.schedule {
+        Schedule::Interval(interval) => {
+            if *interval == 0 {
+                panic_with_error!(env, Error::InvalidInterval);
+            }
+            if config.last_run.checked_add(*interval).is_none() {
+                panic_with_error!(env, Error::Overflow);
+            }
+        }
+        Schedule::Cron(expr) => {
+            // Also rejects expressions that can never fire, like `0 0 30 2 *`
+            let fires = parse_cron(expr)
+                .and_then(|cron| cron.next_after(env.ledger().timestamp()))
+                .is_some();
+            if !fires || config.clock != Clock::Timestamp {
+                panic_with_error!(env, Error::InvalidSchedule);
+            }
+        }
+        Schedule::Timestamps(timestamps) => {
+            if timestamps.is_empty() || timestamps.len() > MAX_SCHEDULE_TIMESTAMPS {
+                panic_with_error!(env, Error::InvalidSchedule);
+            }
+            let mut previous = None;
+            for timestamp in timestamps.iter() {
+                if previous.is_some_and(|previous| timestamp <= previous) {
+                    panic_with_error!(env, Error::InvalidSchedule);
+                }
+                previous = Some(timestamp);
+            }
+        }
+    }
+
+    if let (Some(start_at), Some(end_at)) = (config.start_at, config.end_at) {
+        if end_at < start_at {
+            panic_with_error!(env, Error::InvalidSchedule);
+        }
+    }
+    if config.max_runs == Some(0) {
+        panic_with_error!(env, Error::InvalidSchedule);
+    }
+    if config.retry.max_retries > 0
+        && (config.retry.base_backoff == 0 || config.retry.multiplier == 0)
+    {
+        panic_with_error!(env, Error::InvalidSchedule);
+    }
+    if config.history_size > MAX_HISTORY_SIZE {
+        panic_with_error!(env, Error::HistoryTooLarge);
+    }
+    if let CatchUp::Backfill(runs) = config.catch_up {
+        if runs > MAX_BACKFILL_RUNS {
+            panic_with_error!(env, Error::InvalidSchedule);
+        }
+    }
+    let resolver_args = config.resolver_args.as_ref().map_or(0, |args| args.len());
+    if config.args.len() > MAX_ARGS || resolver_args > MAX_ARGS {
+        panic_with_error!(env, Error::ArgsTooLarge);
+    }
+    if config.whitelist.len() > MAX_WHITELIST_SIZE {
+        panic_with_error!(env, Error::WhitelistTooLarge);
+    }
+    if config.allowed_functions.len() > MAX_ALLOWED_FUNCTIONS {
+        panic_with_error!(env, Error::AllowedFunctionsTooLarge);
+    }
+    if !(0..=MAX_REWARD).contains(&config.reward) {
+        panic_with_error!(env, Error::InvalidAmount);
+    }
+    // The contract is authorized for its own calls, so a task calling the
+    // gas token or this contract could spend every task's escrow
+    let token: Option<Address> = env.storage().instance().get(&DataKey::Token);
+    if config.target == env.current_contract_address() || token.is_some_and(|t| t == config.target)
+    {
+        panic_with_error!(env, Error::InvalidTarget);
+    }
+}
+
+/// Rejects a [`Schedule::Timestamps`] whose times have all passed, as it
+/// would never fire. Only checked when the schedule is set: the last time
+/// of a list passes while the task waits for its final run.
+fn validate_new_schedule(env: &Env, config: &TaskConfig) {
+    if let Schedule::Timestamps(timestamps) = &config.schedule {
+        if timestamps
+            .last()
+            .is_some_and(|last| last <= clock_now(env, config.clock))
+        {
+            panic_with_error!(env, Error::InvalidSchedule);
+        }
+    }
+}
+
+/// Returns the next slot if its execution window closed before `now`.
+/// Retries do not extend the window of the slot they are for.
+fn missed_slot(config: &TaskConfig, now: u64) -> Option<u64> {
+    let window = config.execution_window?;
+    next_slot(config).filter(|slot| now > slot.saturating_add(window))
+}
+
+/// Runs every check that precedes the target call, in the order `execute`
+/// applies them. Shared by `execute` and the read-only `can_execute` view.
+///
+/// On success returns the function and args to call the target with.
+fn check_task(
+    env: &Env,
+    keeper: &Address,
+    task_id: u64,
+    config: &TaskConfig,
+) -> Result<(Symbol, Vec<Val>), ExecutionCheck> {
+    match config.status {
+        TaskStatus::Active if is_finished(env, config) => return Err(ExecutionCheck::Completed),
+        TaskStatus::Active => {}
+        TaskStatus::Paused => return Err(ExecutionCheck::Paused),
+        TaskStatus::Completed => return Err(ExecutionCheck::Completed),
+    }
+
+    if !config.whitelist.is_empty() && !config.whitelist.contains(keeper) {
+        return Err(ExecutionCheck::Unauthorized);
+    }
+
+    let due = next_due(config).unwrap_or(u64::MAX);
+    let now = clock_now(env, config.clock);
+    if now < due {
+        return Err(ExecutionCheck::NotDue(due));
+    }
+    if let Some(slot) = missed_slot(config, now) {
+        return Err(ExecutionCheck::SlotMissed(slot));
+    }
+
+    // The reward and rent fee must be fully covered by the escrowed balance
+    // before we spend anything on the resolver or the target.
+    if config.gas_balance < run_payout(env, config) {
+        return Err(ExecutionCheck::InsufficientBalance);
+    }
+
+    // ── Resolver gate ────────────────────────────────────────────────────────
+    // When a resolver is present we use try_invoke_contract so that an
+    // error inside the resolver (panic / wrong return type) is reported as
+    // ResolverErrored rather than aborting the whole tx.
+    //
+    // The resolver's interface is:  check_condition(args: Vec<Val>) -> bool
+    // Its first argument is the task's args vector (or `resolver_args` when
+    // set), so we must pack it into the outer Vec<Val> as one element —
+    // otherwise the host would unpack it as individual positional
+    // arguments, causing an argument-count mismatch. When
+    // `resolver_context` is set the resolver is called as
+    // check_condition(args: Vec<Val>, context: ExecutionContext) instead.
+    //
+    // A plan resolver (`plan_execution(args: Vec<Val>) -> ExecutionPlan`)
+    // may also replace the function and args of the target call. Picking a
+    // function the creator did not allow counts as a resolver error.
+    let context = ExecutionContext {
+        task_id,
+        last_run: config.last_run,
+        run_count: config.run_count,
+        keeper: keeper.clone(),
+        timestamp: env.ledger().timestamp(),
+    };
+    let mut function = config.function.clone();
+    let mut args = config.args.clone();
+    if let Some(ref resolver_address) = config.resolver {
+        let resolver_args = config.resolver_args.clone().unwrap_or(config.args.clone());
+        let mut resolver_call_args = Vec::<Val>::new(env);
+        resolver_call_args.push_back(resolver_args.into_val(env));
+        if config.resolver_context {
+            resolver_call_args.push_back(context.clone().into_val(env));
+        }
+        match config.resolver_kind {
+            ResolverKind::Condition => match env.try_invoke_contract::<bool, soroban_sdk::Error>(
+                resolver_address,
+                &Symbol::new(env, "check_condition"),
+                resolver_call_args,
+            ) {
+                Ok(Ok(true)) => {}
+                Ok(Ok(false)) => return Err(ExecutionCheck::ResolverDenied),
+                _ => return Err(ExecutionCheck::ResolverErrored),
+            },
+            ResolverKind::Plan => {
+                let plan = match env.try_invoke_contract::<ExecutionPlan, soroban_sdk::Error>(
+                    resolver_address,
+                    &Symbol::new(env, "plan_execution"),
+                    resolver_call_args,
+                ) {
+                    Ok(Ok(plan)) => plan,
+                    _ => return Err(ExecutionCheck::ResolverErrored),
+                };
+                if !plan.execute {
+                    return Err(ExecutionCheck::ResolverDenied);
+                }
+                if let Some(planned) = plan.function {
+                    if planned != function && !config.allowed_functions.contains(&planned) {
+                        return Err(ExecutionCheck::ResolverErrored);
+                    }
+                    function = planned;
+                }
+                if let Some(planned) = plan.args {
+                    args = planned;
+                }
+            }
+        }
+    }
+
+    Ok((function, fill_placeholders(env, args, &context)))
+}
+
+/// Runs a single task on behalf of an already authorized `keeper`.
+///
+/// Checks that reject the call are reported as `Err` before any state is
+/// written. The target is called through `try_invoke_contract`, so a
+/// failing target is recorded on the task and yields
+/// [`ExecutionOutcome::TargetFailed`] instead of reverting the transaction.
+fn run_task(env: &Env, keeper: &Address, task_id: u64) -> Result<ExecutionOutcome, Error> {
+    let mut config = load_task(env, task_id).ok_or(Error::TaskNotFound)?;
+
+    // A task past the end of its lifetime is completed by whoever shows up
+    // next, so the creator can reclaim the leftover balance.
+    if config.status == TaskStatus::Active && is_finished(env, &config) {
+        let before = config.clone();
+        complete_task(env, task_id, &mut config);
+        save_task(env, task_id, &config);
+        reschedule(env, task_id, &before, &config);
+        return Ok(ExecutionOutcome::Completed);
+    }
+
+    let (function, args) = match check_task(env, keeper, task_id, &config) {
+        Ok(call) => call,
+        // Readiness is reported as `Ok` by check_task
+        Err(ExecutionCheck::Ready) => unreachable!(),
+        Err(ExecutionCheck::TaskNotFound) => return Err(Error::TaskNotFound),
+        Err(ExecutionCheck::Paused) => return Err(Error::TaskPaused),
+        Err(ExecutionCheck::Completed) => return Err(Error::TaskCompleted),
+        Err(ExecutionCheck::Unauthorized) => return Err(Error::Unauthorized),
+        Err(ExecutionCheck::InsufficientBalance) => return Err(Error::InsufficientBalance),
+        Err(ExecutionCheck::NotDue(due)) => return Ok(ExecutionOutcome::NotDue(due)),
+        Err(ExecutionCheck::SlotMissed(due)) => {
+            let before = config.clone();
+            let now = clock_now(env, config.clock);
+            config.retry_at = None;
+            config.retry_count = 0;
+            let window = config.execution_window.unwrap_or_default();
+            let missed = skip_missed_slots(&mut config, due, now, window);
+            config.missed_slots = config.missed_slots.saturating_add(missed);
+            env.events().publish(
+                (Symbol::new(env, "SlotMissed"), task_id),
+                (missed, config.missed_slots),
+            );
+            if is_finished(env, &config) {
+                complete_task(env, task_id, &mut config);
+            }
+            save_task(env, task_id, &config);
+            reschedule(env, task_id, &before, &config);
+            return Ok(ExecutionOutcome::SlotMissed(missed));
+        }
+        Err(ExecutionCheck::ResolverDenied) => return Ok(ExecutionOutcome::ResolverDenied),
+        Err(ExecutionCheck::ResolverErrored) => return Ok(ExecutionOutcome::ResolverErrored),
+    };
+
+    let before = config.clone();
+    let now = clock_now(env, config.clock);
+    // A retry runs for the slot that failed
+    config.retry_at = None;
+    let slot = next_due(&config).unwrap_or(now);
+
+    // ── Cross-contract call ──────────────────────────────────────────────────
+    // `args` is Vec<Val> as stored in TaskConfig, or as planned by the
+    // resolver — passed directly.
+    // The return value is discarded; callers can read target state
+    // independently if needed.
+    //
+    // A failure pays nothing. It is retried as the task's RetryPolicy
+    // allows, after which the slot is given up. After `max_failures` in a
+    // row the task is paused until its creator resumes it.
+    match env.try_invoke_contract::<Val, soroban_sdk::Error>(&config.target, &function, args) {
+        Ok(Ok(_)) => {}
+        failure => {
+            let code = match failure {
+                Err(Ok(error)) => error.get_code(),
+                _ => 0,
+            };
+            config.consecutive_failures = config.consecutive_failures.saturating_add(1);
+            let policy = &config.retry;
+            if config.retry_count < policy.max_retries {
+                let factor = (policy.multiplier as u64).saturating_pow(config.retry_count);
+                let backoff = policy.base_backoff.saturating_mul(factor);
+                config.retry_at = Some(now.saturating_add(backoff));
+                config.retry_count += 1;
+            } else {
+                config.retry_count = 0;
+                config.last_run = match config.anchor {
+                    Anchor::Execution => now,
+                    Anchor::Schedule => anchored_slot(&config, slot, now),
+                };
+            }
+            env.events().publish(
+                (Symbol::new(env, "TaskFailed"), task_id),
+                (keeper.clone(), code, config.consecutive_failures),
+            );
+            if config
+                .max_failures
+                .is_some_and(|max| config.consecutive_failures >= max)
+            {
+                config.status = TaskStatus::Paused;
+                env.events().publish(
+                    (Symbol::new(env, "TaskAutoPaused"), task_id),
+                    config.consecutive_failures,
+                );
+            }
+            save_task(env, task_id, &config);
+            reschedule(env, task_id, &before, &config);
+            return Ok(ExecutionOutcome::TargetFailed(code));
+        }
+    }
+
+    // ── State update ─────────────────────────────────────────────────────────
+    // Reached only when the target call returned successfully.
+    // Record the clock reading of this successful execution, or the slot
+    // it stands for when the task is anchored to its schedule.
+    config.last_run = match config.anchor {
+        Anchor::Execution => now,
+        Anchor::Schedule => anchored_slot(&config, slot, now),
+    };
+    config.run_count = config.run_count.checked_add(1).ok_or(Error::Overflow)?;
+    config.consecutive_failures = 0;
+    config.retry_count = 0;
+
+    // ── Keeper payout ────────────────────────────────────────────────────────
+    // The reward leaves the task's escrow only after the target call
+    // succeeded, so a reverted run never costs the creator anything.
+    // The rent fee rides along with the reward.
+    let payout = run_payout(env, &config);
+    if payout > 0 {
+        gas_token(env).transfer(&env.current_contract_address(), keeper, &payout);
+        config.gas_balance -= payout;
+
+        env.events().publish(
+            (Symbol::new(env, "KeeperPaid"), task_id),
+            (keeper.clone(), payout),
+        );
+    }
+
+    if is_finished(env, &config) {
+        complete_task(env, task_id, &mut config);
+    }
+
+    save_task(env, task_id, &config);
+    reschedule(env, task_id, &before, &config);
+
+    Ok(ExecutionOutcome::Executed)
+}
+
+/// Bookkeeping after every attempt that did not revert: publishes the
+/// outcome, records it in the history and keeps the task's entries alive.
+fn finish_run(env: &Env, task_id: u64, keeper: &Address, outcome: &ExecutionOutcome) {
+    publish_outcome(env, task_id, keeper, outcome);
+    if let Some(config) = load_task(env, task_id) {
+        record_history(env, task_id, keeper, outcome, &config);
+        extend_task_ttl(env, task_id, &config);
+    }
+}
+
+/// Appends an attempt to the task's execution history, dropping the oldest
+/// records beyond its `history_size`. Probes that found the task not due
+/// are not recorded.
+fn record_history(
+    env: &Env,
+    task_id: u64,
+    keeper: &Address,
+    outcome: &ExecutionOutcome,
+    config: &TaskConfig,
+) {
+    if config.history_size == 0 || matches!(outcome, ExecutionOutcome::NotDue(_)) {
+        return;
+    }
+
+    let history_key = DataKey::History(task_id);
+    let mut history: Vec<ExecutionRecord> = env
+        .storage()
+        .persistent()
+        .get(&history_key)
+        .unwrap_or(Vec::new(env));
+    history.push_back(ExecutionRecord {
+        timestamp: env.ledger().timestamp(),
+        keeper: keeper.clone(),
+        outcome: outcome.clone(),
+        reward: match outcome {
+            ExecutionOutcome::Executed => run_payout(env, config),
+            _ => 0,
+        },
+    });
+    while history.len() > config.history_size {
+        history.pop_front();
+    }
+    env.storage().persistent().set(&history_key, &history);
+}
+
+/// Emits the event matching the outcome of a run so that execution logs can
+/// show every attempt, not only successful ones.
+fn publish_outcome(env: &Env, task_id: u64, keeper: &Address, outcome: &ExecutionOutcome) {
+    let name = match outcome {
+        ExecutionOutcome::Executed => "TaskExecuted",
+        ExecutionOutcome::NotDue(next_due) => {
+            env.events().publish(
+                (Symbol::new(env, "TaskNotDue"), task_id),
+                (keeper.clone(), *next_due),
+            );
+            return;
+        }
+        ExecutionOutcome::ResolverDenied => "ResolverDenied",
+        ExecutionOutcome::ResolverErrored => "ResolverErrored",
+        // TaskCompleted, SlotMissed and TaskFailed are published with the
+        // state change
+        ExecutionOutcome::Completed
+        | ExecutionOutcome::SlotMissed(_)
+        | ExecutionOutcome::TargetFailed(_) => return,
+        // Rejected runs revert or are reported through the batch result
+        ExecutionOutcome::Rejected(_) => return,
+    };
+    env.events()
+        .publish((Symbol::new(env, name), task_id), keeper.clone());
+}
+
+#[contract]
+pub struct SoroTaskContract;
+
+#[contractimpl]
+impl SoroTaskContract {
+    /// Registers a new task in the marketplace.
+    /// Returns the unique sequential ID of the registered task.
+    ///
+    /// A positive `initial_deposit` is transferred from the creator into
+    /// the task's gas balance.
+    pub fn register(env: Env, spec: TaskSpec, initial_deposit: i128) -> u64 {
+        // Ensure the creator has authorized the registration
+        spec.creator.require_auth();
+        if initial_deposit < 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        // Validate the task configuration
+        let mut config = new_task(&env, spec);
+        validate_config(&env, &config);
+        validate_new_schedule(&env, &config);
+
+        // Generate a unique sequential ID
+        let mut counter: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Counter)
+            .unwrap_or(0);
+        counter = checked(&env, counter.checked_add(1));
+        env.storage().persistent().set(&DataKey::Counter, &counter);
+
+        if initial_deposit > 0 {
+            gas_token(&env).transfer(
+                &config.creator,
+                &env.current_contract_address(),
+                &initial_deposit,
+            );
+            config.gas_balance = initial_deposit;
+        }
+
+        // Store the task configuration
+        save_task(&env, counter, &config);
+
+        // Index the task under its creator
+        let creator_key = DataKey::CreatorTasks(config.creator.clone());
+        let mut creator_tasks: Vec<u64> = env
+            .storage()
+            .persistent()
+            .get(&creator_key)
+            .unwrap_or(Vec::new(&env));
+        creator_tasks.push_back(counter);
+        env.storage().persistent().set(&creator_key, &creator_tasks);
+
+        // Queue the task for its first run
+        if let Some(bucket) = queue_bucket(&env, &config) {
+            enqueue(&env, counter, bucket);
+        }
+
+        let ttl = ttl_config(&env);
+        env.storage()
+            .persistent()
+            .extend_ttl(&DataKey::Counter, ttl.threshold, ttl.extend_to);
+        extend_task_ttl(&env, counter, &config);
+
+        // Emit TaskRegistered event
+        env.events().publish(
+            (Symbol::new(&env, "TaskRegistered"), counter),
+            config.creator.clone(),
+        );
+        if initial_deposit > 0 {
+            env.events().publish(
+                (Symbol::new(&env, "GasDeposited"), counter),
+                (config.creator, initial_deposit),
+            );
+        }
+
+        counter
+    }
+
+    /// Retrieves a task configuration by its ID.
+    pub fn get_task(env: Env, task_id: u64) -> Option<TaskConfig> {
+        load_task(&env, task_id)
+    }
+
+    /// Extends the TTL of a task's storage entries. Anyone can call it to
+    /// keep a rarely executed task from being archived.
+    ///
+    /// Entries that were already archived must first be restored with a
+    /// `RestoreFootprint` operation; bumping afterwards keeps them live.
+    pub fn bump_task(env: Env, task_id: u64) {
+        let config = require_task(&env, task_id);
+        extend_task_ttl(&env, task_id, &config);
+    }
+
+    /// Returns the TTL configuration in effect.
+    pub fn get_ttl_config(env: Env) -> TtlConfig {
+        ttl_config(&env)
+    }
+
+    /// Returns up to `limit` of a task's latest execution records, newest
+    /// first.
+    pub fn get_history(env: Env, task_id: u64, limit: u32) -> Vec<ExecutionRecord> {
+        let history: Vec<ExecutionRecord> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::History(task_id))
+            .unwrap_or(Vec::new(&env));
+
+        let mut latest = Vec::new(&env);
+        for record in history.iter().rev().take(limit as usize) {
+            latest.push_back(record);
+        }
+        latest
+    }
+
+    /// Returns the number of task IDs issued so far, including tasks that
+    /// have since been cancelled.
+    pub fn task_count(env: Env) -> u64 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Counter)
+            .unwrap_or(0)
+    }
+
+    /// Lists registered tasks in ascending ID order, starting after
+    /// `start_after` (use `0` for the first page).
+    ///
+    /// A page ends after `limit` tasks, capped at [`MAX_PAGE_SIZE`], or after
+    /// probing [`MAX_LIST_SCAN`] IDs, so a long run of cancelled tasks cannot
+    /// make a call unboundedly expensive. Keep passing the returned `next`
+    /// as `start_after` until it is `None`; a page may be empty before then.
+    pub fn list_tasks(env: Env, start_after: u64, limit: u32) -> TaskPage {
+        let limit = limit.min(MAX_PAGE_SIZE);
+        let counter = Self::task_count(env.clone());
+        let last_id = counter.min(start_after.saturating_add(MAX_LIST_SCAN));
+
+        let mut tasks = Vec::new(&env);
+        let mut task_id = start_after;
+        while tasks.len() < limit && task_id < last_id {
+            task_id += 1;
+            if let Some(config) = load_task(&env, task_id) {
+                tasks.push_back((task_id, config));
+            }
+        }
+        TaskPage {
+            tasks,
+            next: (task_id < counter).then_some(task_id),
+        }
+    }
+
+    /// Lists the tasks registered by `creator` in ascending ID order,
+    /// starting after `start_after`. At most `limit` entries are returned,
+    /// capped at [`MAX_PAGE_SIZE`].
+    pub fn tasks_by_creator(
+        env: Env,
+        creator: Address,
+        start_after: u64,
+        limit: u32,
+    ) -> Vec<(u64, TaskConfig)> {
+        let limit = limit.min(MAX_PAGE_SIZE);
+        let creator_tasks: Vec<u64> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::CreatorTasks(creator))
+            .unwrap_or(Vec::new(&env));
+
+        // IDs are appended in registration order, so the index is sorted
+        let start = match creator_tasks.binary_search(start_after) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+
+        let mut tasks = Vec::new(&env);
+        for task_id in creator_tasks.slice(start..).iter() {
+            if tasks.len() == limit {
+                break;
+            }
+            let config = require_task(&env, task_id);
+            tasks.push_back((task_id, config));
+        }
+        tasks
+    }
+
+    /// Updates the execution parameters of an existing task.
+    /// Only the task creator can update; `last_run` and `gas_balance` are
+    /// left untouched.
+    pub fn update_task(env: Env, task_id: u64, update: TaskUpdate) {
+        let mut config = require_task(&env, task_id);
+
+        config.creator.require_auth();
+
+        let before = config.clone();
+        let mut changed = Vec::<Symbol>::new(&env);
+        if let Some(target) = update.target {
+            config.target = target;
+            changed.push_back(Symbol::new(&env, "target"));
+        }
+        if let Some(function) = update.function {
+            config.function = function;
+            changed.push_back(Symbol::new(&env, "function"));
+        }
+        if let Some(args) = update.args {
+            config.args = args;
+            changed.push_back(Symbol::new(&env, "args"));
+        }
+        match update.resolver {
+            ResolverUpdate::Keep => {}
+            ResolverUpdate::Set(address) => {
+                config.resolver = Some(address);
+                config.resolver_kind = ResolverKind::Condition;
+                changed.push_back(Symbol::new(&env, "resolver"));
+            }
+            ResolverUpdate::SetPlan(address) => {
+                config.resolver = Some(address);
+                config.resolver_kind = ResolverKind::Plan;
+                changed.push_back(Symbol::new(&env, "resolver"));
+            }
+            ResolverUpdate::Clear => {
+                config.resolver = None;
+                changed.push_back(Symbol::new(&env, "resolver"));
+            }
+        }
+        let schedule_set = matches!(update.schedule, ScheduleUpdate::Set(_));
+        if let ScheduleUpdate::Set(schedule) = update.schedule {
+            config.schedule = schedule;
+            // A pending retry belongs to a slot of the old schedule
+            config.retry_at = None;
+            config.retry_count = 0;
+            changed.push_back(Symbol::new(&env, "schedule"));
+        }
+        if let Some(whitelist) = update.whitelist {
+            config.whitelist = whitelist;
+            changed.push_back(Symbol::new(&env, "whitelist"));
+        }
+        if let Some(allowed_functions) = update.allowed_functions {
+            config.allowed_functions = allowed_functions;
+            changed.push_back(Symbol::new(&env, "allowed_functions"));
+        }
+        if let Some(history_size) = update.history_size {
+            config.history_size = history_size;
+            changed.push_back(Symbol::new(&env, "history_size"));
+        }
+
+        validate_config(&env, &config);
+        if schedule_set {
+            validate_new_schedule(&env, &config);
+        }
+
+        let history_key = DataKey::History(task_id);
+        if let Some(mut history) = env
+            .storage()
+            .persistent()
+            .get::<_, Vec<ExecutionRecord>>(&history_key)
+        {
+            if history.len() > config.history_size {
+                while history.len() > config.history_size {
+                    history.pop_front();
+                }
+                env.storage().persistent().set(&history_key, &history);
+            }
+        }
+
+        save_task(&env, task_id, &config);
+        reschedule(&env, task_id, &before, &config);
+
+        env.events()
+            .publish((Symbol::new(&env, "TaskUpdated"), task_id), changed);
+    }
+
+    /// Pauses a task so keepers can no longer execute it. The task keeps its
+    /// ID, history and gas balance. Only the task creator can pause, and not
+    /// while a cancellation is pending.
+    pub fn pause_task(env: Env, task_id: u64) {
+        let mut config = require_task(&env, task_id);
+
+        config.creator.require_auth();
+
+        if config.status == TaskStatus::Completed {
+            panic_with_error!(&env, Error::TaskCompleted);
+        }
+        if config.cancel_at.is_some() {
+            panic_with_error!(&env, Error::CancellationPending);
+        }
+
+        let before = config.clone();
+        config.status = TaskStatus::Paused;
+        save_task(&env, task_id, &config);
+        reschedule(&env, task_id, &before, &config);
+
+        env.events()
+            .publish((Symbol::new(&env, "TaskPaused"), task_id), ());
+    }
+
+    /// Resumes a paused task. Only the task creator can resume, and only a
+    /// task that is paused: anything else fails with [`Error::TaskNotPaused`]
+    /// (or [`Error::TaskCompleted`]) so its failure count is left alone.
+    ///
+    /// With `reset_clock` set, `last_run` is moved to the current reading of
+    /// the task's [`Clock`] (the ledger timestamp or sequence) so the next run
+    /// is a full interval away; otherwise the task is due again as soon as its
+    /// original schedule allows.
+    pub fn resume_task(env: Env, task_id: u64, reset_clock: bool) {
+        let mut config = require_task(&env, task_id);
+
+        config.creator.require_auth();
+
+        match config.status {
+            TaskStatus::Paused => {}
+            TaskStatus::Completed => panic_with_error!(&env, Error::TaskCompleted),
+            TaskStatus::Active => panic_with_error!(&env, Error::TaskNotPaused),
+        }
+
+        let before = config.clone();
+        config.status = TaskStatus::Active;
+        config.consecutive_failures = 0;
+        if reset_clock {
+            config.last_run = clock_now(&env, config.clock);
+            config.retry_at = None;
+            config.retry_count = 0;
+        }
+        save_task(&env, task_id, &config);
+        reschedule(&env, task_id, &before, &config);
+
+        env.events()
+            .publish((Symbol::new(&env, "TaskResumed"), task_id), config.last_run);
+    }
+
+    /// Returns the tasks that are due at the current ledger, each by its own
+    /// clock, scanning the due queue from `cursor` (use [`DueCursor::Start`]
+    /// for the first page). Tasks come in order of their due bucket, and by
+    /// ascending ID within a bucket. Paused tasks, tasks whose `gas_balance`
+    /// cannot cover their `reward`, and tasks that `execute` would complete
+    /// or skip as a missed slot without paying the keeper are skipped.
+    ///
+    /// A page ends after `limit` tasks, capped at [`MAX_PAGE_SIZE`], or after
+    /// [`MAX_DUE_SCAN`] queued tasks were examined, whichever comes first.
+    /// Pass the returned `next` cursor back to continue; it is
+    /// [`DueCursor::End`] once the whole queue was scanned.
+    ///
+    /// Each entry carries the task's reward so keepers can rank the work by
+    /// profitability before submitting.
+    ///
+    /// Only queue entries after the cursor and up to the current bucket are
+    /// read, and only funded tasks are queued, so the cost of a page follows
+    /// [`MAX_DUE_SCAN`] rather than the number of registered tasks.
+    pub fn get_due_tasks(env: Env, cursor: DueCursor, limit: u32) -> DuePage {
+        let limit = limit.min(MAX_PAGE_SIZE);
+        let storage = env.storage().persistent();
+
+        let clocks = [Clock::Timestamp, Clock::LedgerSequence];
+        let (first_clock, mut resume) = match cursor {
+            DueCursor::Start => (0, None),
+            DueCursor::After(clock, bucket, task_id) => (clock as usize, Some((bucket, task_id))),
+            DueCursor::End => (clocks.len(), None),
+        };
+
+        let mut tasks = Vec::new(&env);
+        let mut next = cursor;
+        let mut scanned = 0;
+        for clock in clocks[first_clock..].iter().copied() {
+            let now = clock_now(&env, clock);
+            let last_bucket = now / bucket_width(clock);
+            // Task IDs start at 1, so `(0, 0)` skips nothing
+            let (first_bucket, after_id) = resume.take().unwrap_or((0, 0));
+
+            let ranges: Vec<u64> = storage
+                .get(&DataKey::DueRanges(clock))
+                .unwrap_or(Vec::new(&env));
+            for range in ranges.iter() {
+                if range < first_bucket / BUCKETS_PER_RANGE {
+                    continue;
+                }
+                if range > last_bucket / BUCKETS_PER_RANGE {
+                    break;
+                }
+                let buckets: Vec<u64> = storage
+                    .get(&DataKey::DueBuckets(clock, range))
+                    .unwrap_or(Vec::new(&env));
+                for bucket in buckets.iter() {
+                    if bucket < first_bucket {
+                        continue;
+                    }
+                    if bucket > last_bucket {
+                        break;
+                    }
+                    let resumed = bucket == first_bucket;
+                    let pages: Vec<u64> = storage
+                        .get(&DataKey::DuePages(clock, bucket))
+                        .unwrap_or(Vec::new(&env));
+                    for page in pages.iter() {
+                        if resumed && page < after_id / DUE_PAGE_SIZE {
+                            continue;
+                        }
+                        let task_ids: Vec<u64> = storage
+                            .get(&DataKey::DueBucket(clock, bucket, page))
+                            .unwrap_or(Vec::new(&env));
+                        for task_id in task_ids.iter() {
+                            if resumed && task_id <= after_id {
+                                continue;
+                            }
+                            if tasks.len() == limit || scanned == MAX_DUE_SCAN {
+                                return DuePage { tasks, next };
+                            }
+                            scanned += 1;
+                            next = DueCursor::After(clock, bucket, task_id);
+
+                            let config = require_task(&env, task_id);
+                            let reward = run_payout(&env, &config);
+                            if config.status != TaskStatus::Active
+                                || config.gas_balance < reward
+                                || next_due(&config).is_none_or(|due| due > now)
+                                || is_finished(&env, &config)
+                                || missed_slot(&config, now).is_some()
+                            {
+                                continue;
+                            }
+                            tasks.push_back(DueTask { task_id, reward });
+                        }
+                    }
+                }
+            }
+        }
+        DuePage {
+            tasks,
+            next: DueCursor::End,
+        }
+    }
+
+    /// Reports whether `execute(keeper, task_id)` would invoke the target
+    /// right now, running the same checks including a resolver probe.
+    /// Never panics, so keepers can simulate it before paying for a
+    /// transaction.
+    pub fn can_execute(env: Env, keeper: Address, task_id: u64) -> ExecutionCheck {
+        match load_task(&env, task_id) {
+            Some(config) => match check_task(&env, &keeper, task_id, &config) {
+                Ok(_) => ExecutionCheck::Ready,
+                Err(check) => check,
+            },
+            None => ExecutionCheck::TaskNotFound,
+        }
+    }
+
+    /// Executes a registered task identified by `task_id`.
+    ///
+    /// # Flow
+    /// 1. Load the [`TaskConfig`] from persistent storage (fails with
+    ///    [`Error::TaskNotFound`] if absent).
+    /// 2. If a `resolver` address is set, call `check_condition(args) -> bool`
+    ///    on it via [`try_invoke_contract`] so that a faulty resolver never
+    ///    aborts the transaction — a failed or panicking call is reported as
+    ///    [`ExecutionOutcome::ResolverErrored`] and the target is not called.
+    ///    A [`ResolverKind::Plan`] resolver is asked for an [`ExecutionPlan`]
+    ///    through `plan_execution(args)` instead, which may replace the
+    ///    function and args of the call.
+    /// 3. When the condition is met (or there is no resolver), fire the
+    ///    cross-contract call to `target::function(args)` using
+    ///    [`try_invoke_contract`]. A failed call is counted in
+    ///    `consecutive_failures`, retried as the task's [`RetryPolicy`]
+    ///    allows and reported as [`ExecutionOutcome::TargetFailed`]; reaching
+    ///    `max_failures` pauses the task.
+    /// 4. Only on a **successful** invocation persist the updated `last_run`
+    ///    timestamp and pay the keeper `reward` out of the task's
+    ///    `gas_balance`.
+    ///
+    /// Returns an [`ExecutionOutcome`] telling the keeper whether the target
+    /// ran, and if not why, and emits a matching event.
+    ///
+    /// # Safety & Atomicity
+    /// The target runs in its own frame: if it panics only its own state
+    /// changes are rolled back, and `SoroTask` records the failure instead.
+    /// `last_run` is written **after** the cross-contract call returns
+    /// successfully, guaranteeing it only reflects completed executions.
+    pub fn execute(env: Env, keeper: Address, task_id: u64) -> ExecutionOutcome {
+        keeper.require_auth();
+        match run_task(&env, &keeper, task_id) {
+            Ok(outcome) => {
+                finish_run(&env, task_id, &keeper, &outcome);
+                outcome
+            }
+            Err(error) => panic_with_error!(&env, error),
+        }
+    }
+
+    /// Executes several tasks in one transaction on behalf of `keeper`,
+    /// applying the same checks as [`Self::execute`] to each of them.
+    /// Returns one [`ExecutionOutcome`] per entry of `task_ids`.
+    ///
+    /// In [`BatchMode::BestEffort`] a task that is rejected or whose target
+    /// fails is reported in the outcome vector and the remaining tasks still
+    /// run; the failed target's own state changes are rolled back. In
+    /// [`BatchMode::AllOrNothing`] the first failure reverts the whole batch,
+    /// with [`Error::TargetFailed`] for a failing target.
+    pub fn execute_batch(
+        env: Env,
+        keeper: Address,
+        task_ids: Vec<u64>,
+        mode: BatchMode,
+    ) -> Vec<ExecutionOutcome> {
+        keeper.require_auth();
+
+        if task_ids.len() > MAX_BATCH_SIZE {
+            panic_with_error!(&env, Error::BatchTooLarge);
+        }
+
+        let best_effort = mode == BatchMode::BestEffort;
+        let mut outcomes = Vec::new(&env);
+        for task_id in task_ids.iter() {
+            let outcome = match run_task(&env, &keeper, task_id) {
+                Ok(ExecutionOutcome::TargetFailed(_)) if !best_effort => {
+                    panic_with_error!(&env, Error::TargetFailed)
+                }
+                Ok(outcome) => {
+                    finish_run(&env, task_id, &keeper, &outcome);
+                    outcome
+                }
+                Err(error) if best_effort => ExecutionOutcome::Rejected(error as u32),
+                Err(error) => panic_with_error!(&env, error),
+            };
+            outcomes.push_back(outcome);
+        }
+        outcomes
+    }
+
+    /// Cancels a task and refunds its remaining `gas_balance` to the creator.
+    /// Only the task creator can cancel.
+    ///
+    /// Tasks registered with a non-zero `cancel_notice` are cancelled in two
+    /// steps: the first call records `cancel_at` and the task stays
+    /// executable until then, so keepers already working on a run are still
+    /// paid. Meanwhile the creator can neither pause the task nor withdraw
+    /// its gas. Calling again once `cancel_at` has passed finalises the
+    /// cancellation.
+    pub fn cancel_task(env: Env, task_id: u64) {
+        let mut config = require_task(&env, task_id);
+
+        config.creator.require_auth();
+
+        // Completed tasks have no keeper in flight and are refunded at once
+        let now = env.ledger().timestamp();
+        match config.cancel_at {
+            None if config.cancel_notice > 0 && config.status != TaskStatus::Completed => {
+                let cancel_at = checked(&env, now.checked_add(config.cancel_notice));
+                config.cancel_at = Some(cancel_at);
+                save_task(&env, task_id, &config);
+
+                env.events()
+                    .publish((Symbol::new(&env, "CancelRequested"), task_id), cancel_at);
+                return;
+            }
+            Some(cancel_at) if now < cancel_at => {
+                panic_with_error!(&env, Error::CancellationPending);
+            }
+            _ => {}
+        }
+
+        // Refund whatever is left in escrow
+        let refund = config.gas_balance;
+        if refund > 0 {
+            gas_token(&env).transfer(&env.current_contract_address(), &config.creator, &refund);
+        }
+
+        env.storage().persistent().remove(&DataKey::Task(task_id));
+        env.storage()
+            .persistent()
+            .remove(&DataKey::History(task_id));
+        if let Some(bucket) = due_bucket(&config) {
+            dequeue(&env, task_id, bucket);
+        }
+
+        // Drop the task from its creator's index
+        let creator_key = DataKey::CreatorTasks(config.creator.clone());
+        let mut creator_tasks: Vec<u64> = env
+            .storage()
+            .persistent()
+            .get(&creator_key)
+            .unwrap_or(Vec::new(&env));
+        if let Some(i) = creator_tasks.first_index_of(task_id) {
+            creator_tasks.remove(i);
+        }
+        if creator_tasks.is_empty() {
+            env.storage().persistent().remove(&creator_key);
+        } else {
+            env.storage().persistent().set(&creator_key, &creator_tasks);
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "TaskCancelled"), task_id),
+            (config.creator, refund),
+        );
+    }
+
+    /// Initializes the contract with its admin and gas token. The admin
+    /// must authorize the call.
+    ///
+    /// Whoever calls `init` first becomes the admin, so deploy the contract
+    /// and call `init` in the same transaction.
+    pub fn init(env: Env, admin: Address, token: Address) {
+        admin.require_auth();
+        if env.storage().instance().has(&DataKey::Token) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::Token, &token);
+    }
+
+    /// Replaces the contract code with the uploaded Wasm `new_wasm_hash`,
+    /// keeping storage and the contract address. Admin only.
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
+        require_admin(&env);
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        env.events()
+            .publish((Symbol::new(&env, "Upgraded"),), new_wasm_hash);
+    }
+
+    /// Returns the version of the running contract code.
+    pub fn version(_env: Env) -> u32 {
+        CONTRACT_VERSION
+    }
+
+    /// Rewrites a task in the current storage format and adds it to the
+    /// creator and due indexes where it is missing, as are tasks registered
+    /// by the first release or queued before the due queue was paged.
+    /// Anyone can call it; running it again changes nothing.
+    pub fn migrate_task(env: Env, task_id: u64) {
+        let config = require_task(&env, task_id);
+        save_task(&env, task_id, &config);
+
+        let creator_key = DataKey::CreatorTasks(config.creator.clone());
+        let mut creator_tasks: Vec<u64> = env
+            .storage()
+            .persistent()
+            .get(&creator_key)
+            .unwrap_or(Vec::new(&env));
+        if let Err(i) = creator_tasks.binary_search(task_id) {
+            creator_tasks.insert(i, task_id);
+            env.storage().persistent().set(&creator_key, &creator_tasks);
+        }
+
+        if let Some(bucket) = queue_bucket(&env, &config) {
+            enqueue(&env, task_id, bucket);
+        }
+        extend_task_ttl(&env, task_id, &config);
+    }
+
+    /// Returns the current admin.
+    pub fn get_admin(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::NotInitialized))
+    }
+
+    /// Proposes `new_admin` as the next admin. Takes effect once it calls
+    /// [`Self::accept_admin`]; a later proposal replaces this one.
+    pub fn propose_admin(env: Env, new_admin: Address) {
+        let admin = require_admin(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingAdmin, &new_admin);
+        env.events()
+            .publish((Symbol::new(&env, "AdminProposed"),), (admin, new_admin));
+    }
+
+    /// Completes an admin handover. Must be authorized by the proposed
+    /// admin.
+    pub fn accept_admin(env: Env) {
+        let new_admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PendingAdmin)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::NoPendingAdmin));
+        new_admin.require_auth();
+
+        let old_admin = Self::get_admin(env.clone());
+        env.storage().instance().set(&DataKey::Admin, &new_admin);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        env.events()
+            .publish((Symbol::new(&env, "AdminChanged"),), (old_admin, new_admin));
+    }
+
+    /// Sets how storage TTLs are extended. Admin only.
+    ///
+    /// The due queue only holds tasks that can pay for a run, but changing
+    /// `rent_fee` does not re-queue anything: tasks it underfunds drop out
+    /// on their next balance change, and tasks it funds join on their next
+    /// deposit or [`Self::migrate_task`].
+    pub fn set_ttl_config(env: Env, config: TtlConfig) {
+        require_admin(&env);
+        if config.threshold > config.extend_to
+            || config.extend_to > env.storage().max_ttl()
+            || !(0..=MAX_RENT_FEE).contains(&config.rent_fee)
+        {
+            panic_with_error!(&env, Error::InvalidTtlConfig);
+        }
+        env.storage().instance().set(&DataKey::TtlConfig, &config);
+    }
+
+    /// Deposits gas tokens to a task's balance.
+    pub fn deposit_gas(env: Env, task_id: u64, from: Address, amount: i128) {
+        from.require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let mut config = require_task(&env, task_id);
+        let gas_balance = checked(&env, config.gas_balance.checked_add(amount));
+
+        // Transfer tokens to contract
+        gas_token(&env).transfer(&from, &env.current_contract_address(), &amount);
+
+        // Update balance, queueing the task once it can pay for a run.
+        // Queueing is idempotent, which also picks up tasks funded by a
+        // lowered rent fee.
+        config.gas_balance = gas_balance;
+        save_task(&env, task_id, &config);
+        if let Some(bucket) = queue_bucket(&env, &config) {
+            enqueue(&env, task_id, bucket);
+        }
+        extend_task_ttl(&env, task_id, &config);
+
+        // Emit event
+        env.events()
+            .publish((Symbol::new(&env, "GasDeposited"), task_id), (from, amount));
+    }
+
+    /// Withdraws gas tokens from a task's balance.
+    /// Only the task creator can withdraw.
+    pub fn withdraw_gas(env: Env, task_id: u64, amount: i128) {
+        let mut config = require_task(&env, task_id);
+
+        // Ensure only creator can withdraw
+        config.creator.require_auth();
+
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+        // The balance backs the runs of the notice period; the final
+        // cancellation refunds it
+        if config.cancel_at.is_some() {
+            panic_with_error!(&env, Error::CancellationPending);
+        }
+        if config.gas_balance < amount {
+            panic_with_error!(&env, Error::InsufficientBalance);
+        }
+
+        // Transfer tokens back to creator
+        gas_token(&env).transfer(&env.current_contract_address(), &config.creator, &amount);
+
+        // Update balance
+        let before = config.clone();
+        config.gas_balance -= amount;
+        save_task(&env, task_id, &config);
+        reschedule(&env, task_id, &before, &config);
+
+        // Emit event
+        env.events().publish(
+            (Symbol::new(&env, "GasWithdrawn"), task_id),
+            (config.creator.clone(), amount),
+        );
+    }
+
+    /// Returns the global gas token address.
+    pub fn get_token(env: Env) -> Address {
+        gas_token(&env).address
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use soroban_sdk::{
+        contract, contractimpl,
+        testutils::{
+            storage::Instance as _, storage::Persistent as _, Address as _, Events, Ledger as _,
+            MockAuth, MockAuthInvoke,
+        },
+        vec, Env, FromVal, IntoVal,
+    };
+
+    // ── Mock Contracts ───────────────────────────────────────────────────────
+
+    #[contract]
+    pub struct DummyContract;
+
+    #[contractimpl]
+    impl DummyContract {
+        pub fn hello(_env: Env) {}
+    }
+
+    /// Minimal target contract with two callable functions.
+    #[contract]
+    pub struct MockTarget;
+
+    #[contractimpl]
+    impl MockTarget {
+        /// Zero-argument smoke-test function.
+        pub fn ping(_env: Env) -> bool {
+            true
+        }
+
+        /// Two-argument function — verifies args are forwarded correctly.
+        pub fn add(_env: Env, a: i64, b: i64) -> i64 {
+            a + b
+        }
+
+        /// Always panics — simulates a broken target.
+        pub fn fail(_env: Env) {
+            panic!("target failure");
+        }
+
+        /// Stores its arguments, meant to be filled from placeholders.
+        pub fn record_run(env: Env, run: u32, timestamp: u64) {
+            env.storage()
+                .instance()
+                .set(&Symbol::new(&env, "run"), &(run, timestamp));
+        }
+
+        /// Stores `value` so tests can see which call was made.
+        pub fn record(env: Env, value: i64) {
+            env.storage()
+                .instance()
+                .set(&Symbol::new(&env, "recorded"), &value);
+        }
+    }
+
+    fn recorded(env: &Env, target: &Address) -> Option<i64> {
+        env.as_contract(target, || {
+            env.storage().instance().get(&Symbol::new(env, "recorded"))
+        })
+    }
+
+    // ── Resolver contracts (separate sub-modules) ───────────────────────
+
+    /// Resolver taking a context; approves the first two runs when given
+    /// a single argument.
+    mod resolver_context {
+        use crate::ExecutionContext;
+        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+
+        #[contract]
+        pub struct MockContextResolver;
+
+        #[contractimpl]
+        impl MockContextResolver {
+            pub fn check_condition(_env: Env, args: Vec<Val>, context: ExecutionContext) -> bool {
+                args.len() == 1 && context.run_count < 2
+            }
+        }
+    }
+
+    /// Resolver that always approves execution.
+    mod resolver_true {
+        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+
+        #[contract]
+        pub struct MockResolverTrue;
+
+        #[contractimpl]
+        impl MockResolverTrue {
+            pub fn check_condition(_env: Env, _args: Vec<Val>) -> bool {
+                true
+            }
+        }
+    }
+
+    /// Resolver returning whatever plan was last stored with `set_plan`.
+    mod resolver_plan {
+        use crate::ExecutionPlan;
+        use soroban_sdk::{contract, contractimpl, Env, Symbol, Val, Vec};
+
+        #[contract]
+        pub struct MockPlanResolver;
+
+        #[contractimpl]
+        impl MockPlanResolver {
+            pub fn set_plan(env: Env, plan: ExecutionPlan) {
+                env.storage()
+                    .instance()
+                    .set(&Symbol::new(&env, "plan"), &plan);
+            }
+
+            pub fn plan_execution(env: Env, _args: Vec<Val>) -> ExecutionPlan {
+                env.storage()
+                    .instance()
+                    .get(&Symbol::new(&env, "plan"))
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Resolver that always denies execution.
+    mod resolver_false {
+        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+
+        #[contract]
+        pub struct MockResolverFalse;
+
+        #[contractimpl]
+        impl MockResolverFalse {
+            pub fn check_condition(_env: Env, _args: Vec<Val>) -> bool {
+                false
+            }
+        }
+    }
+
+    /// Resolver that panics instead of answering.
+    mod resolver_panic {
+        use soroban_sdk::{contract, contractimpl, Env, Val, Vec};
+
+        #[contract]
+        pub struct MockResolverPanic;
+
+        #[contractimpl]
+        impl MockResolverPanic {
+            pub fn check_condition(_env: Env, _args: Vec<Val>) -> bool {
+                panic!("resolver failure");
+            }
+        }
+    }
+
+    // ── Helpers ──────────────────────────────────────────────────────────────
+
+    fn setup() -> (Env, Address) {
+        let env = Env::default();
+        env.mock_all_auths();
+        let id = env.register_contract(None, SoroTaskContract);
+        (env, id)
+    }
+
+    fn base_spec(env: &Env, target: Address) -> TaskSpec {
+        TaskSpec {
+            creator: Address::generate(env),
+            target,
+            function: Symbol::new(env, "ping"),
+            args: Vec::new(env),
+            resolver: None,
+            schedule: Schedule::Interval(3_600),
+            whitelist: Vec::new(env),
+            reward: 0,
+            cancel_notice: 0,
+            start_at: None,
+            end_at: None,
+            max_runs: None,
+            anchor: Anchor::Execution,
+            catch_up: CatchUp::SkipToLatest,
+            execution_window: None,
+            clock: Clock::Timestamp,
+            max_failures: None,
+            retry: RetryPolicy {
+                max_retries: 0,
+                base_backoff: 0,
+                multiplier: 0,
+            },
+            history_size: 0,
+            resolver_args: None,
+            resolver_context: false,
+            resolver_kind: ResolverKind::Condition,
+            allowed_functions: Vec::new(env),
+        }
+    }
+
+    /// Registers a Stellar asset as the gas token and returns its address.
+    fn setup_token(env: &Env, client: &SoroTaskContractClient) -> Address {
+        let token_id = env.register_stellar_asset_contract_v2(Address::generate(env));
+        let token_address = token_id.address();
+        client.init(&Address::generate(env), &token_address);
+        token_address
+    }
+
+    fn set_timestamp(env: &Env, ts: u64) {
+        env.ledger().with_mut(|l| l.timestamp = ts);
+    }
+
+    // ── Tests ─────────────────────────────────────────────────────────────────
+
+    /// Registering a task stores it; get_task retrieves identical data.
+    #[test]
+    fn test_register_and_get_task() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target.clone());
+        let task_id = client.register(&cfg, &0);
+
+        let stored = client.get_task(&task_id).expect("task should exist");
+        assert_eq!(stored.target, target);
+        assert_eq!(stored.schedule, Schedule::Interval(3_600));
+        assert_eq!(stored.last_run, 0, "last_run must start at 0");
+    }
+
+    /// The contract sets the bookkeeping fields; the deposit moves tokens.
+    #[test]
+    fn test_register_initialises_state_and_takes_deposit() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+        let token_client = soroban_sdk::token::Client::new(&env, &token);
+
+        let target = env.register_contract(None, MockTarget);
+        let spec = base_spec(&env, target);
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&spec.creator, &1_000);
+
+        set_timestamp(&env, 5_000);
+        let task_id = client.register(&spec, &400);
+        let data: (Address, i128) = last_event_named(&env, "GasDeposited")
+            .unwrap()
+            .into_val(&env);
+        assert_eq!(data, (spec.creator.clone(), 400));
+
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.last_run, task.gas_balance), (5_000, 400));
+        assert_eq!((task.run_count, task.status), (0, TaskStatus::Active));
+        assert_eq!(token_client.balance(&spec.creator), 600);
+        assert_eq!(token_client.balance(&id), 400);
+
+        // The first run is one interval after registration
+        assert_eq!(
+            client.can_execute(&Address::generate(&env), &task_id),
+            ExecutionCheck::NotDue(8_600)
+        );
+    }
+
+    /// Querying a task id that was never registered returns None.
+    #[test]
+    fn test_get_task_missing_returns_none() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        assert!(client.get_task(&99_u64).is_none());
+    }
+
+    /// A successful cross-contract call updates last_run to the ledger timestamp.
+    #[test]
+    fn test_execute_invokes_target_and_updates_last_run() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 12_345);
+        client.execute(&keeper, &task_id);
+
+        let updated = client.get_task(&task_id).unwrap();
+        assert_eq!(
+            updated.last_run, 12_345,
+            "last_run must reflect ledger timestamp after execution"
+        );
+    }
+
+    /// Args stored in TaskConfig are forwarded correctly to the target function.
+    #[test]
+    fn test_execute_forwards_args_to_target() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+
+        let mut args: Vec<Val> = Vec::new(&env);
+        args.push_back(5_i64.into_val(&env));
+        args.push_back(3_i64.into_val(&env));
+
+        let cfg = TaskSpec {
+            function: Symbol::new(&env, "add"),
+            args,
+            schedule: Schedule::Interval(60),
+            ..base_spec(&env, target)
+        };
+
+        let task_id = client.register(&cfg, &0);
+        let keeper = Address::generate(&env);
+        set_timestamp(&env, 99_999);
+        client.execute(&keeper, &task_id);
+
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 99_999);
+    }
+
+    #[test]
+    fn test_context_resolver_and_arg_placeholders() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let resolver = env.register_contract(None, resolver_context::MockContextResolver);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "record_run"),
+                args: vec![
+                    &env,
+                    ArgPlaceholder::RunNumber.into_val(&env),
+                    ArgPlaceholder::Timestamp.into_val(&env),
+                ],
+                resolver: Some(resolver),
+                resolver_args: Some(vec![&env, 7_u32.into_val(&env)]),
+                resolver_context: true,
+                schedule: Schedule::Interval(60),
+                ..base_spec(&env, target.clone())
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+        let last_recorded = || -> (u32, u64) {
+            env.as_contract(&target, || {
+                env.storage()
+                    .instance()
+                    .get(&Symbol::new(&env, "run"))
+                    .unwrap()
+            })
+        };
+
+        set_timestamp(&env, 100);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(last_recorded(), (1, 100));
+
+        set_timestamp(&env, 170);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(last_recorded(), (2, 170));
+
+        set_timestamp(&env, 240);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::ResolverDenied
+        );
+    }
+
+    #[test]
+    fn test_plan_resolver_overrides_call() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let resolver = env.register_contract(None, resolver_plan::MockPlanResolver);
+        let resolver_client = resolver_plan::MockPlanResolverClient::new(&env, &resolver);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                resolver: Some(resolver.clone()),
+                resolver_kind: ResolverKind::Plan,
+                allowed_functions: vec![&env, Symbol::new(&env, "record")],
+                schedule: Schedule::Interval(60),
+                ..base_spec(&env, target.clone())
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        resolver_client.set_plan(&ExecutionPlan {
+            execute: false,
+            function: None,
+            args: None,
+        });
+        set_timestamp(&env, 100);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::ResolverDenied
+        );
+
+        // The static call would panic; the planned one records 42
+        resolver_client.set_plan(&ExecutionPlan {
+            execute: true,
+            function: Some(Symbol::new(&env, "record")),
+            args: Some(vec![&env, 42_i64.into_val(&env)]),
+        });
+        assert_eq!(client.can_execute(&keeper, &task_id), ExecutionCheck::Ready);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(recorded(&env, &target), Some(42));
+
+        // Functions outside the allowed list are refused
+        resolver_client.set_plan(&ExecutionPlan {
+            execute: true,
+            function: Some(Symbol::new(&env, "ping")),
+            args: Some(Vec::new(&env)),
+        });
+        set_timestamp(&env, 200);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::ResolverErrored
+        );
+    }
+
+    /// When a resolver returns true the target is invoked and last_run updated.
+    #[test]
+    fn test_execute_with_resolver_true_proceeds() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let resolver = env.register_contract(None, resolver_true::MockResolverTrue);
+
+        let cfg = TaskSpec {
+            resolver: Some(resolver),
+            ..base_spec(&env, target)
+        };
+
+        let task_id = client.register(&cfg, &0);
+        let keeper = Address::generate(&env);
+        set_timestamp(&env, 55_000);
+        client.execute(&keeper, &task_id);
+
+        assert_eq!(
+            client.get_task(&task_id).unwrap().last_run,
+            55_000,
+            "resolver approved — last_run must be updated"
+        );
+    }
+
+    /// When a resolver returns false the target is NOT invoked and last_run is
+    /// left unchanged.
+    #[test]
+    fn test_execute_with_resolver_false_skips_invocation() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let resolver = env.register_contract(None, resolver_false::MockResolverFalse);
+
+        let cfg = TaskSpec {
+            resolver: Some(resolver),
+            ..base_spec(&env, target)
+        };
+
+        let task_id = client.register(&cfg, &0);
+        let keeper = Address::generate(&env);
+        set_timestamp(&env, 77_777);
+        client.execute(&keeper, &task_id);
+
+        assert_eq!(
+            client.get_task(&task_id).unwrap().last_run,
+            0,
+            "resolver denied — last_run must not change"
+        );
+    }
+
+    /// Calling execute multiple times updates last_run on every successful run.
+    #[test]
+    fn test_execute_repeated_calls_update_timestamp_each_time() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let mut cfg = base_spec(&env, target);
+        cfg.schedule = Schedule::Interval(1); // Small interval to allow repeated execution
+        let task_id = client.register(&cfg, &0);
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 1_000);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 1_000);
+
+        set_timestamp(&env, 2_000);
+        client.execute(&keeper, &task_id);
+        assert_eq!(
+            client.get_task(&task_id).unwrap().last_run,
+            2_000,
+            "last_run must advance on each execution"
+        );
+    }
+
+    #[test]
+    fn test_register_and_get() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, SoroTaskContract);
+        let client = SoroTaskContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let target = Address::generate(&env);
+
+        let config = TaskSpec {
+            creator: creator.clone(),
+            function: Symbol::new(&env, "hello"),
+            args: vec![&env, 0i128.into_val(&env)],
+            ..base_spec(&env, target.clone())
+        };
+
+        let task_id = client.register(&config, &0);
+        assert_eq!(task_id, 1);
+
+        let retrieved_config = client.get_task(&task_id).unwrap();
+        assert_eq!(retrieved_config.creator, config.creator);
+        assert_eq!(retrieved_config.target, config.target);
+        assert_eq!(retrieved_config.function, config.function);
+        assert_eq!(retrieved_config.schedule, config.schedule);
+        assert_eq!(retrieved_config.gas_balance, 0);
+
+        // Check event
+        let events = env.events().all();
+        let last_event = events.last().unwrap();
+
+        assert_eq!(last_event.0, contract_id);
+
+        let topics = last_event.1.clone();
+        assert_eq!(
+            Symbol::from_val(&env, &topics.get(0).unwrap()),
+            Symbol::new(&env, "TaskRegistered")
+        );
+        assert_eq!(u64::from_val(&env, &topics.get(1).unwrap()), 1u64);
+
+        let data: Address = last_event.2.clone().into_val(&env);
+        assert_eq!(data, creator);
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, SoroTaskContract);
+        let client = SoroTaskContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let target = Address::generate(&env);
+
+        let config = TaskSpec {
+            creator: creator.clone(),
+            function: Symbol::new(&env, "hello"),
+            ..base_spec(&env, target.clone())
+        };
+
+        let id1 = client.register(&config, &0);
+        let id2 = client.register(&config, &0);
+
+        assert_eq!(id1, 1);
+        assert_eq!(id2, 2);
+    }
+
+    #[test]
+    fn test_register_invalid_interval() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, SoroTaskContract);
+        let client = SoroTaskContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let target = Address::generate(&env);
+
+        let config = TaskSpec {
+            creator: creator.clone(),
+            function: Symbol::new(&env, "hello"),
+            schedule: Schedule::Interval(0), // Invalid
+            ..base_spec(&env, target.clone())
+        };
+
+        let result = client.try_register(&config, &0);
+        assert_eq!(result, Err(Ok(soroban_sdk::Error::from_contract_error(1))));
+    }
+
+    #[test]
+    fn test_execute_honors_interval() {
         let env = Env::default();
         env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, SoroTaskContract);
+        let client = SoroTaskContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let dummy_id = env.register_contract(None, DummyContract);
+        let target = dummy_id.clone();
+
+        let config = TaskSpec {
+            creator: creator.clone(),
+            function: Symbol::new(&env, "hello"),
+            schedule: Schedule::Interval(100),
+            ..base_spec(&env, target.clone())
+        };
+
+        let task_id = client.register(&config, &0);
+        let keeper = Address::generate(&env);
+
+        // First execution (ledger 50, last_run 0, interval 100)
+        // 50 < 0 + 100 -> returns early
+        env.ledger().set_timestamp(50);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 0);
+
+        env.ledger().set_timestamp(150);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 150);
+
+        // Next execution too soon
+        env.ledger().set_timestamp(200);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 150);
+    }
+
+    #[test]
+    fn test_gas_management_lifecycle() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let token_admin = Address::generate(&env);
+        let token_id = env.register_stellar_asset_contract_v2(token_admin.clone());
+        let token_address = token_id.address();
+        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
+        let token_admin_client = soroban_sdk::token::StellarAssetClient::new(&env, &token_address);
+
+        client.init(&Address::generate(&env), &token_address);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let creator = cfg.creator.clone();
+        let task_id = client.register(&cfg, &0);
+
+        // Mint tokens to creator
+        token_admin_client.mint(&creator, &5000);
+        assert_eq!(token_client.balance(&creator), 5000);
+
+        // Deposit gas
+        client.deposit_gas(&task_id, &creator, &2000);
+        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 2000);
+        assert_eq!(token_client.balance(&creator), 3000);
+        assert_eq!(token_client.balance(&id), 2000);
+
+        // Withdraw gas
+        client.withdraw_gas(&task_id, &500);
+        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 1500);
+        assert_eq!(token_client.balance(&creator), 3500);
+    }
+
+    #[test]
+    fn test_gas_amounts_must_be_positive() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+        let token_client = soroban_sdk::token::Client::new(&env, &token);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let creator = cfg.creator.clone();
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&creator, &1_000);
+        let task_id = client.register(&cfg, &500);
+
+        let invalid = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::InvalidAmount as u32,
+        )));
+        assert_eq!(
+            client.try_deposit_gas(&task_id, &creator, &0).err(),
+            invalid
+        );
+        assert_eq!(
+            client.try_deposit_gas(&task_id, &creator, &-5).err(),
+            invalid
+        );
+        assert_eq!(client.try_withdraw_gas(&task_id, &0).err(), invalid);
+        // A negative withdrawal must not grow the balance
+        assert_eq!(client.try_withdraw_gas(&task_id, &-500).err(), invalid);
+        assert_eq!(client.try_register(&cfg, &-1).err(), invalid);
+        assert_eq!(
+            client
+                .try_register(
+                    &TaskSpec {
+                        reward: -1,
+                        ..cfg.clone()
+                    },
+                    &0
+                )
+                .err(),
+            invalid
+        );
+        assert_eq!(
+            client
+                .try_register(
+                    &TaskSpec {
+                        reward: MAX_REWARD + 1,
+                        ..cfg.clone()
+                    },
+                    &0
+                )
+                .err(),
+            invalid
+        );
+
+        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 500);
+        assert_eq!(token_client.balance(&creator), 500);
+    }
+
+    /// The largest reward and rent fee still add up, so an unfunded task
+    /// carrying them cannot make the shared views fail.
+    #[test]
+    fn test_max_reward_and_rent_fee_keep_views_working() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        setup_token(&env, &client);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                reward: MAX_REWARD,
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        client.set_ttl_config(&TtlConfig {
+            threshold: DEFAULT_TTL_THRESHOLD,
+            extend_to: DEFAULT_TTL_EXTEND_TO,
+            rent_fee: MAX_RENT_FEE,
+        });
+
+        set_timestamp(&env, 3_600);
+        let keeper = Address::generate(&env);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::InsufficientBalance
+        );
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
+    }
+
+    #[test]
+    fn test_overflowing_values_are_rejected() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let overflow = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::Overflow as u32,
+        )));
+
+        // `last_run + interval` would wrap around
+        set_timestamp(&env, 1);
+        let result = client.try_register(
+            &TaskSpec {
+                schedule: Schedule::Interval(u64::MAX),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        assert_eq!(result.err(), overflow);
+
+        let task_id = client.register(
+            &TaskSpec {
+                cancel_notice: u64::MAX,
+                ..cfg.clone()
+            },
+            &0,
+        );
+        assert_eq!(client.try_cancel_task(&task_id).err(), overflow);
+
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&cfg.creator, &i128::MAX);
+        client.deposit_gas(&task_id, &cfg.creator, &i128::MAX);
+        assert_eq!(
+            client.try_deposit_gas(&task_id, &cfg.creator, &1).err(),
+            overflow
+        );
+    }
+
+    /// Tasks cannot call the gas token or the contract itself, which would
+    /// run with the contract's authority over the pooled escrow. Only the
+    /// auths a real transaction would carry are mocked.
+    #[test]
+    fn test_tasks_cannot_target_the_escrow() {
+        let env = Env::default();
         let id = env.register_contract(None, SoroTaskContract);
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token_admin = Address::generate(&env);
+        let token = env
+            .register_stellar_asset_contract_v2(token_admin.clone())
+            .address();
+        let token_client = soroban_sdk::token::Client::new(&env, &token);
+        let admin = Address::generate(&env);
+        let auth = |address: &Address, contract: &Address, fn_name: &str, args: Vec<Val>| {
+            env.mock_auths(&[MockAuth {
+                address,
+                invoke: &MockAuthInvoke {
+                    contract,
+                    fn_name,
+                    args,
+                    sub_invokes: &[],
+                },
+            }]);
+        };
+        auth(&admin, &id, "init", (&admin, &token).into_val(&env));
+        client.init(&admin, &token);
+
+        // A victim funds an ordinary task
+        let target = env.register_contract(None, MockTarget);
+        let victim_spec = base_spec(&env, target.clone());
+        let victim = victim_spec.creator.clone();
+        auth(
+            &token_admin,
+            &token,
+            "mint",
+            (&victim, 1_000_i128).into_val(&env),
+        );
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&victim, &1_000);
+        env.mock_auths(&[MockAuth {
+            address: &victim,
+            invoke: &MockAuthInvoke {
+                contract: &id,
+                fn_name: "register",
+                args: (victim_spec.clone(), 1_000_i128).into_val(&env),
+                sub_invokes: &[MockAuthInvoke {
+                    contract: &token,
+                    fn_name: "transfer",
+                    args: (&victim, &id, 1_000_i128).into_val(&env),
+                    sub_invokes: &[],
+                }],
+            },
+        }]);
+        client.register(&victim_spec, &1_000);
+        assert_eq!(token_client.balance(&id), 1_000);
+
+        // An attacker asks the contract to pay the pool out to them
+        let attacker_spec = base_spec(&env, target);
+        let attacker = attacker_spec.creator.clone();
+        let drain = TaskSpec {
+            target: token.clone(),
+            function: Symbol::new(&env, "transfer"),
+            args: (&id, &attacker, 1_000_i128).into_val(&env),
+            ..attacker_spec.clone()
+        };
+        let invalid_target = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::InvalidTarget as u32,
+        )));
+        auth(
+            &attacker,
+            &id,
+            "register",
+            (drain.clone(), 0_i128).into_val(&env),
+        );
+        assert_eq!(client.try_register(&drain, &0).err(), invalid_target);
+        let reenter = TaskSpec {
+            target: id.clone(),
+            ..attacker_spec.clone()
+        };
+        auth(
+            &attacker,
+            &id,
+            "register",
+            (reenter.clone(), 0_i128).into_val(&env),
+        );
+        assert_eq!(client.try_register(&reenter, &0).err(), invalid_target);
+
+        // Nor can an existing task be pointed at the token later
+        auth(
+            &attacker,
+            &id,
+            "register",
+            (attacker_spec.clone(), 0_i128).into_val(&env),
+        );
+        let task_id = client.register(&attacker_spec, &0);
+        let update = TaskUpdate {
+            target: Some(token.clone()),
+            function: Some(drain.function),
+            args: Some(drain.args),
+            ..empty_update()
+        };
+        auth(
+            &attacker,
+            &id,
+            "update_task",
+            (task_id, update.clone()).into_val(&env),
+        );
+        assert_eq!(
+            client.try_update_task(&task_id, &update).err(),
+            invalid_target
+        );
+
+        assert_eq!(token_client.balance(&id), 1_000);
+        assert_eq!(token_client.balance(&attacker), 0);
+    }
+
+    #[test]
+    fn test_register_rejects_oversized_lists() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let mut args: Vec<Val> = Vec::new(&env);
+        for i in 0..=MAX_ARGS {
+            args.push_back(i.into_val(&env));
+        }
+        let mut whitelist = Vec::new(&env);
+        for _ in 0..=MAX_WHITELIST_SIZE {
+            whitelist.push_back(Address::generate(&env));
+        }
+        let mut allowed_functions = Vec::new(&env);
+        for _ in 0..=MAX_ALLOWED_FUNCTIONS {
+            allowed_functions.push_back(Symbol::new(&env, "record"));
+        }
+
+        let args_too_large = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::ArgsTooLarge as u32,
+        )));
+        let result = client.try_register(
+            &TaskSpec {
+                args: args.clone(),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        assert_eq!(result.err(), args_too_large);
+        let result = client.try_register(
+            &TaskSpec {
+                resolver_args: Some(args.clone()),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        assert_eq!(result.err(), args_too_large);
+
+        let result = client.try_register(
+            &TaskSpec {
+                whitelist,
+                ..cfg.clone()
+            },
+            &0,
+        );
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::WhitelistTooLarge as u32
+            )))
+        );
+
+        let result = client.try_register(
+            &TaskSpec {
+                allowed_functions: allowed_functions.clone(),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let allowed_functions_too_large = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::AllowedFunctionsTooLarge as u32,
+        )));
+        assert_eq!(result.err(), allowed_functions_too_large);
+
+        // The limit also holds when the list is replaced later
+        let task_id = client.register(&cfg, &0);
+        let result = client.try_update_task(
+            &task_id,
+            &TaskUpdate {
+                allowed_functions: Some(allowed_functions),
+                ..empty_update()
+            },
+        );
+        assert_eq!(result.err(), allowed_functions_too_large);
+    }
+
+    #[test]
+    fn test_missing_task_and_token_are_typed_errors() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let not_found = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::TaskNotFound as u32,
+        )));
+        let keeper = Address::generate(&env);
+        assert_eq!(
+            client.try_update_task(&99, &empty_update()).err(),
+            not_found
+        );
+        assert_eq!(client.try_pause_task(&99).err(), not_found);
+        assert_eq!(client.try_resume_task(&99, &false).err(), not_found);
+        assert_eq!(client.try_cancel_task(&99).err(), not_found);
+        assert_eq!(client.try_deposit_gas(&99, &keeper, &10).err(), not_found);
+        assert_eq!(client.try_withdraw_gas(&99, &10).err(), not_found);
+
+        let not_initialized = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::NotInitialized as u32,
+        )));
+        assert_eq!(client.try_get_token().err(), not_initialized);
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        assert_eq!(
+            client.try_deposit_gas(&task_id, &keeper, &10).err(),
+            not_initialized
+        );
+    }
+
+    #[test]
+    fn test_withdraw_gas_insufficient_balance() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let token_id = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        let token_address = token_id.address();
+        client.init(&Address::generate(&env), &token_address);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        soroban_sdk::token::StellarAssetClient::new(&env, &token_address).mint(&cfg.creator, &1000);
+        let task_id = client.register(&cfg, &1000);
+
+        let result = client.try_withdraw_gas(&task_id, &1500);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::InsufficientBalance as u32
+            )))
+        );
+    }
+
+    #[test]
+    fn test_execute_pays_keeper_reward() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let token_id = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        let token_address = token_id.address();
+        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
+        let token_admin_client = soroban_sdk::token::StellarAssetClient::new(&env, &token_address);
+        client.init(&Address::generate(&env), &token_address);
+
+        let target = env.register_contract(None, MockTarget);
+        let mut cfg = base_spec(&env, target);
+        cfg.reward = 50;
+        let creator = cfg.creator.clone();
+        let task_id = client.register(&cfg, &0);
+
+        token_admin_client.mint(&creator, &1_000);
+        client.deposit_gas(&task_id, &creator, &1_000);
+
+        let keeper = Address::generate(&env);
+        set_timestamp(&env, 12_345);
+        client.execute(&keeper, &task_id);
+
+        assert_eq!(token_client.balance(&keeper), 50);
+        assert_eq!(token_client.balance(&id), 950);
+        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 950);
+
+        let data: (Address, i128) = last_event_named(&env, "KeeperPaid").unwrap().into_val(&env);
+        assert_eq!(data, (keeper, 50));
+    }
+
+    #[test]
+    fn test_execute_fails_if_balance_below_reward() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let mut cfg = base_spec(&env, target);
+        cfg.reward = 50;
+        let task_id = client.register(&cfg, &0);
+
+        set_timestamp(&env, 12_345);
+        let result = client.try_execute(&Address::generate(&env), &task_id);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::InsufficientBalance as u32
+            )))
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 0);
+    }
+
+    #[test]
+    fn test_cancel_task_refunds_and_removes() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token_address = setup_token(&env, &client);
+        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
+        let token_admin_client = soroban_sdk::token::StellarAssetClient::new(&env, &token_address);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let creator = cfg.creator.clone();
+        let task_id = client.register(&cfg, &0);
+
+        token_admin_client.mint(&creator, &1_000);
+        client.deposit_gas(&task_id, &creator, &700);
+
+        client.cancel_task(&task_id);
+
+        assert!(client.get_task(&task_id).is_none());
+        assert_eq!(token_client.balance(&creator), 1_000);
+        assert_eq!(token_client.balance(&id), 0);
+
+        let events = env.events().all();
+        let last_event = events.last().unwrap();
+        assert_eq!(
+            Symbol::from_val(&env, &last_event.1.get(0).unwrap()),
+            Symbol::new(&env, "TaskCancelled")
+        );
+        let data: (Address, i128) = last_event.2.clone().into_val(&env);
+        assert_eq!(data, (creator, 700));
+    }
+
+    #[test]
+    fn test_cancel_task_honors_notice_period() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+        let token_client = soroban_sdk::token::Client::new(&env, &token);
+
+        let target = env.register_contract(None, MockTarget);
+        let mut cfg = base_spec(&env, target);
+        cfg.cancel_notice = 600;
+        cfg.reward = 10;
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&cfg.creator, &100);
+        let task_id = client.register(&cfg, &100);
+
+        set_timestamp(&env, 10_000);
+        client.cancel_task(&task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().cancel_at, Some(10_600));
+
+        // The creator cannot pull the balance or the task from under a
+        // keeper during the notice period
+        let pending = Some(Ok(soroban_sdk::Error::from_contract_error(
+            Error::CancellationPending as u32,
+        )));
+        assert_eq!(client.try_withdraw_gas(&task_id, &100).err(), pending);
+        assert_eq!(client.try_pause_task(&task_id).err(), pending);
+
+        // Keepers can still run the task while the notice period is running
+        let keeper = Address::generate(&env);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 10_000);
+        assert_eq!(token_client.balance(&keeper), 10);
+
+        set_timestamp(&env, 10_599);
+        assert_eq!(client.try_cancel_task(&task_id).err(), pending);
+
+        set_timestamp(&env, 10_600);
+        client.cancel_task(&task_id);
+        assert!(client.get_task(&task_id).is_none());
+        assert_eq!(token_client.balance(&cfg.creator), 90);
+    }
+
+    fn empty_update() -> TaskUpdate {
+        TaskUpdate {
+            target: None,
+            function: None,
+            args: None,
+            resolver: ResolverUpdate::Keep,
+            schedule: ScheduleUpdate::Keep,
+            whitelist: None,
+            allowed_functions: None,
+            history_size: None,
+        }
+    }
+
+    #[test]
+    fn test_update_task_changes_fields_and_keeps_state() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let resolver = env.register_contract(None, resolver_true::MockResolverTrue);
+        let task_id = client.register(&base_spec(&env, target.clone()), &0);
+
+        set_timestamp(&env, 5_000);
+        client.execute(&Address::generate(&env), &task_id);
+
+        let update = TaskUpdate {
+            function: Some(Symbol::new(&env, "add")),
+            args: Some(vec![&env, 1_i64.into_val(&env), 2_i64.into_val(&env)]),
+            resolver: ResolverUpdate::Set(resolver.clone()),
+            schedule: ScheduleUpdate::Set(Schedule::Interval(60)),
+            ..empty_update()
+        };
+        client.update_task(&task_id, &update);
+
+        let updated = client.get_task(&task_id).unwrap();
+        assert_eq!(updated.target, target);
+        assert_eq!(updated.function, Symbol::new(&env, "add"));
+        assert_eq!(updated.args.len(), 2);
+        assert_eq!(updated.resolver, Some(resolver));
+        assert_eq!(updated.schedule, Schedule::Interval(60));
+        assert_eq!(updated.last_run, 5_000);
+        assert_eq!(updated.run_count, 1);
+
+        let events = env.events().all();
+        let last_event = events.last().unwrap();
+        assert_eq!(
+            Symbol::from_val(&env, &last_event.1.get(0).unwrap()),
+            Symbol::new(&env, "TaskUpdated")
+        );
+        let changed: Vec<Symbol> = last_event.2.clone().into_val(&env);
+        assert_eq!(
+            changed,
+            vec![
+                &env,
+                Symbol::new(&env, "function"),
+                Symbol::new(&env, "args"),
+                Symbol::new(&env, "resolver"),
+                Symbol::new(&env, "schedule"),
+            ]
+        );
+
+        // The updated interval applies from the preserved last_run
+        set_timestamp(&env, 5_060);
+        client.execute(&Address::generate(&env), &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 5_060);
+
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                resolver: ResolverUpdate::Clear,
+                ..empty_update()
+            },
+        );
+        assert!(client.get_task(&task_id).unwrap().resolver.is_none());
+    }
+
+    #[test]
+    fn test_update_task_rejects_invalid_interval() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+
+        let update = TaskUpdate {
+            schedule: ScheduleUpdate::Set(Schedule::Interval(0)),
+            ..empty_update()
+        };
+        let result = client.try_update_task(&task_id, &update);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::InvalidInterval as u32
+            )))
+        );
+        assert_eq!(
+            client.get_task(&task_id).unwrap().schedule,
+            Schedule::Interval(3_600)
+        );
+    }
+
+    #[test]
+    fn test_paused_task_cannot_execute() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        let keeper = Address::generate(&env);
+
+        client.pause_task(&task_id);
+        assert_eq!(
+            client.get_task(&task_id).unwrap().status,
+            TaskStatus::Paused
+        );
+
+        set_timestamp(&env, 12_345);
+        let result = client.try_execute(&keeper, &task_id);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskPaused as u32
+            )))
+        );
+
+        client.resume_task(&task_id, &false);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 12_345);
+    }
+
+    #[test]
+    fn test_resume_task_can_reset_clock() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        let keeper = Address::generate(&env);
+
+        client.pause_task(&task_id);
+        set_timestamp(&env, 10_000);
+        client.resume_task(&task_id, &true);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 10_000);
+
+        // The interval now counts from the resume time
+        set_timestamp(&env, 13_599);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 10_000);
+
+        set_timestamp(&env, 13_600);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 13_600);
+    }
+
+    #[test]
+    fn test_resume_task_rejects_active_task() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+
+        set_timestamp(&env, 4_000);
+        client.execute(&Address::generate(&env), &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().consecutive_failures, 1);
+
+        assert_eq!(
+            client.try_resume_task(&task_id, &true),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskNotPaused as u32
+            )))
+        );
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.consecutive_failures, task.last_run), (1, 4_000));
+    }
+
+    #[test]
+    fn test_list_tasks_paginates_and_skips_cancelled() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        for _ in 0..5 {
+            client.register(&cfg, &0);
+        }
+        assert_eq!(client.task_count(), 5);
+
+        client.cancel_task(&2);
+
+        let page = client.list_tasks(&0, &2);
+        assert_eq!(page.tasks.len(), 2);
+        assert_eq!(page.tasks.get(0).unwrap().0, 1);
+        assert_eq!(page.tasks.get(1).unwrap().0, 3);
+        assert_eq!(page.next, Some(3));
+
+        let page = client.list_tasks(&3, &10);
+        assert_eq!(page.tasks.len(), 2);
+        assert_eq!(page.tasks.get(0).unwrap().0, 4);
+        assert_eq!(page.tasks.get(1).unwrap().0, 5);
+        assert_eq!(page.next, None);
+
+        assert!(client.list_tasks(&5, &10).tasks.is_empty());
+        assert_eq!(client.task_count(), 5, "cancelled IDs are not reused");
+    }
+
+    #[test]
+    fn test_list_tasks_probes_a_bounded_number_of_ids() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        env.budget().reset_unlimited();
+        for task_id in 1..=MAX_LIST_SCAN + 10 {
+            client.register(&cfg, &0);
+            client.cancel_task(&task_id);
+        }
+        let live_id = client.register(&cfg, &0);
+
+        // A run of cancelled IDs yields empty pages that still move on
+        let page = client.list_tasks(&0, &10);
+        assert!(page.tasks.is_empty());
+        assert_eq!(page.next, Some(MAX_LIST_SCAN));
+
+        let page = client.list_tasks(&page.next.unwrap(), &10);
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.tasks.get(0).unwrap().0, live_id);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn test_tasks_by_creator_uses_index() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let alice_cfg = base_spec(&env, target.clone());
+        let bob_cfg = base_spec(&env, target);
+        let alice = alice_cfg.creator.clone();
+        let bob = bob_cfg.creator.clone();
+
+        client.register(&alice_cfg, &0); // 1
+        client.register(&bob_cfg, &0); // 2
+        client.register(&alice_cfg, &0); // 3
+        client.register(&alice_cfg, &0); // 4
+
+        let ids = |page: Vec<(u64, TaskConfig)>| {
+            let mut ids = Vec::<u64>::new(&env);
+            for (task_id, _) in page.iter() {
+                ids.push_back(task_id);
+            }
+            ids
+        };
+
+        assert_eq!(
+            ids(client.tasks_by_creator(&alice, &0, &10)),
+            vec![&env, 1, 3, 4]
+        );
+        assert_eq!(ids(client.tasks_by_creator(&alice, &1, &1)), vec![&env, 3]);
+        assert_eq!(ids(client.tasks_by_creator(&bob, &0, &10)), vec![&env, 2]);
+
+        client.cancel_task(&3);
+        assert_eq!(
+            ids(client.tasks_by_creator(&alice, &0, &10)),
+            vec![&env, 1, 4]
+        );
+
+        client.cancel_task(&2);
+        assert!(client.tasks_by_creator(&bob, &0, &10).is_empty());
+    }
+
+    #[test]
+    fn test_get_due_tasks_filters_and_reports_reward() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let token = setup_token(&env, &client);
+        let target = env.register_contract(None, MockTarget);
+        let mut cfg = base_spec(&env, target);
+        cfg.reward = 10;
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&cfg.creator, &10_000);
+
+        let due_id = client.register(&cfg, &100); // due at 3_600
+        let paused_id = client.register(&cfg, &100);
+        client.pause_task(&paused_id);
+        let underfunded_id = client.register(&cfg, &5);
+        let later_id = client.register(
+            &TaskSpec {
+                start_at: Some(7_600),
+                ..cfg.clone()
+            },
+            &100,
+        );
+        let rich_id = client.register(
+            &TaskSpec {
+                reward: 25,
+                ..cfg.clone()
+            },
+            &100,
+        );
+
+        set_timestamp(&env, 3_599);
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
+
+        set_timestamp(&env, 3_600);
+        let due = client.get_due_tasks(&DueCursor::Start, &10).tasks;
+        assert_eq!(
+            due,
+            vec![
+                &env,
+                DueTask {
+                    task_id: due_id,
+                    reward: 10
+                },
+                DueTask {
+                    task_id: rich_id,
+                    reward: 25
+                },
+            ]
+        );
+        assert!(!due.iter().any(|t| t.task_id == paused_id
+            || t.task_id == underfunded_id
+            || t.task_id == later_id));
+
+        // Paging resumes after the cursor
+        let page = client.get_due_tasks(&DueCursor::Start, &1);
+        assert_eq!(page.tasks, vec![&env, due.get(0).unwrap()]);
+        let page = client.get_due_tasks(&page.next, &1);
+        assert_eq!(page.tasks, vec![&env, due.get(1).unwrap()]);
+        let page = client.get_due_tasks(&page.next, &1);
+        assert_eq!((page.tasks.len(), page.next), (0, DueCursor::End));
+
+        set_timestamp(&env, 7_600);
+        assert_eq!(client.get_due_tasks(&DueCursor::Start, &10).tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_get_due_tasks_skips_unpaid_outcomes() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let due_id = client.register(&cfg, &0);
+        let ended_id = client.register(
+            &TaskSpec {
+                end_at: Some(3_650),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let windowed_id = client.register(
+            &TaskSpec {
+                execution_window: Some(60),
+                ..cfg.clone()
+            },
+            &0,
+        );
+
+        set_timestamp(&env, 3_600);
+        assert_eq!(client.get_due_tasks(&DueCursor::Start, &10).tasks.len(), 3);
+
+        set_timestamp(&env, 3_700);
+        assert_eq!(
+            client.get_due_tasks(&DueCursor::Start, &10).tasks,
+            vec![
+                &env,
+                DueTask {
+                    task_id: due_id,
+                    reward: 0
+                }
+            ]
+        );
+        let keeper = Address::generate(&env);
+        assert_eq!(
+            client.execute(&keeper, &ended_id),
+            ExecutionOutcome::Completed
+        );
+        assert_eq!(
+            client.execute(&keeper, &windowed_id),
+            ExecutionOutcome::SlotMissed(1)
+        );
+    }
+
+    /// Returns the non-empty buckets of the timestamp due queue, in order.
+    fn due_buckets(env: &Env, contract_id: &Address) -> Vec<u64> {
+        env.as_contract(contract_id, || {
+            let storage = env.storage().persistent();
+            let ranges: Vec<u64> = storage
+                .get(&DataKey::DueRanges(Clock::Timestamp))
+                .unwrap_or(Vec::new(env));
+            let mut queued = Vec::new(env);
+            for range in ranges.iter() {
+                let buckets: Vec<u64> = storage
+                    .get(&DataKey::DueBuckets(Clock::Timestamp, range))
+                    .unwrap();
+                queued.append(&buckets);
+            }
+            queued
+        })
+    }
+
+    #[test]
+    fn test_due_queue_follows_task_lifecycle() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let task_id = client.register(&cfg, &0);
+        let first_bucket = 3_600 / BUCKET_SECONDS;
+        assert_eq!(due_buckets(&env, &id), vec![&env, first_bucket]);
+
+        // A run moves the task to the bucket of its next due time
+        set_timestamp(&env, 10_000);
+        client.execute(&Address::generate(&env), &task_id);
+        let next_bucket = 13_600 / BUCKET_SECONDS;
+        assert_eq!(due_buckets(&env, &id), vec![&env, next_bucket]);
+
+        // Paused tasks leave the queue and come back on resume
+        client.pause_task(&task_id);
+        assert!(due_buckets(&env, &id).is_empty());
+        client.resume_task(&task_id, &false);
+        assert_eq!(due_buckets(&env, &id), vec![&env, next_bucket]);
+
+        // Interval changes re-queue the task
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                schedule: ScheduleUpdate::Set(Schedule::Interval(86_400)),
+                ..empty_update()
+            },
+        );
+        assert_eq!(
+            due_buckets(&env, &id),
+            vec![&env, (10_000 + 86_400) / BUCKET_SECONDS]
+        );
+
+        client.cancel_task(&task_id);
+        assert!(due_buckets(&env, &id).is_empty());
+    }
+
+    #[test]
+    fn test_due_queue_holds_only_funded_tasks() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = TaskSpec {
+            reward: 10,
+            ..base_spec(&env, target)
+        };
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&cfg.creator, &1_000);
+        let task_id = client.register(&cfg, &0);
+        assert!(due_buckets(&env, &id).is_empty());
+
+        // Funding queues the task, and running it dry drops it again
+        client.deposit_gas(&task_id, &cfg.creator, &15);
+        assert_eq!(due_buckets(&env, &id), vec![&env, 3_600 / BUCKET_SECONDS]);
+        set_timestamp(&env, 3_600);
+        client.execute(&Address::generate(&env), &task_id);
+        assert!(due_buckets(&env, &id).is_empty());
+
+        client.deposit_gas(&task_id, &cfg.creator, &5);
+        assert_eq!(due_buckets(&env, &id), vec![&env, 7_200 / BUCKET_SECONDS]);
+        client.withdraw_gas(&task_id, &1);
+        assert!(due_buckets(&env, &id).is_empty());
+
+        // A rent fee raised after queueing leaves a stale entry that the
+        // next balance change still finds
+        client.deposit_gas(&task_id, &cfg.creator, &1);
+        client.set_ttl_config(&TtlConfig {
+            threshold: DEFAULT_TTL_THRESHOLD,
+            extend_to: DEFAULT_TTL_EXTEND_TO,
+            rent_fee: 5,
+        });
+        assert_eq!(due_buckets(&env, &id), vec![&env, 7_200 / BUCKET_SECONDS]);
+        client.withdraw_gas(&task_id, &1);
+        assert!(due_buckets(&env, &id).is_empty());
+    }
+
+    /// Unfunded tasks are free to register, so they must not slow down the
+    /// lookup of the tasks that are due.
+    #[test]
+    fn test_get_due_tasks_cost_independent_of_unfunded_tasks() {
+        let measure = |unfunded: bool| {
+            let (env, id) = setup();
+            let client = SoroTaskContractClient::new(&env, &id);
+            setup_token(&env, &client);
+            let target = env.register_contract(None, MockTarget);
+            env.budget().reset_unlimited();
+
+            let due_id = client.register(&base_spec(&env, target.clone()), &0);
+            for _ in 0..2 * DUE_PAGE_SIZE {
+                let spec = match unfunded {
+                    true => TaskSpec {
+                        reward: 1,
+                        ..base_spec(&env, target.clone())
+                    },
+                    false => TaskSpec {
+                        start_at: Some(1_000_000),
+                        ..base_spec(&env, target.clone())
+                    },
+                };
+                client.register(&spec, &0);
+            }
+
+            set_timestamp(&env, 3_600);
+            invocation_cost(&env, &id, || {
+                let page = client.get_due_tasks(&DueCursor::Start, &10);
+                assert_eq!(
+                    page.tasks,
+                    vec![
+                        &env,
+                        DueTask {
+                            task_id: due_id,
+                            reward: 0
+                        }
+                    ]
+                );
+            })
+        };
+
+        let (cpu, mem) = measure(true);
+        let (cpu_far, mem_far) = measure(false);
+        assert!(cpu < cpu_far * 11 / 10);
+        assert!(mem < mem_far * 11 / 10);
+    }
+
+    #[test]
+    fn test_get_due_tasks_stops_after_max_scan() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        env.budget().reset_unlimited();
+        // Funded tasks whose window closes unused stay queued until a
+        // keeper skips them
+        for _ in 0..MAX_DUE_SCAN + 10 {
+            client.register(
+                &TaskSpec {
+                    execution_window: Some(60),
+                    ..base_spec(&env, target.clone())
+                },
+                &0,
+            );
+        }
+        let due_id = client.register(&base_spec(&env, target), &0);
+
+        set_timestamp(&env, 3_700);
+        let page = client.get_due_tasks(&DueCursor::Start, &10);
+        let bucket = 3_600 / BUCKET_SECONDS;
+        assert!(page.tasks.is_empty());
+        assert_eq!(
+            page.next,
+            DueCursor::After(Clock::Timestamp, bucket, MAX_DUE_SCAN as u64)
+        );
+
+        let page = client.get_due_tasks(&page.next, &10);
+        assert_eq!(
+            page,
+            DuePage {
+                tasks: vec![
+                    &env,
+                    DueTask {
+                        task_id: due_id,
+                        reward: 0
+                    }
+                ],
+                next: DueCursor::End,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_due_tasks_cost_independent_of_task_count() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let far_future = TaskSpec {
+            start_at: Some(1_000_000),
+            ..cfg.clone()
+        };
+
+        let due_id = client.register(&cfg, &0);
+        set_timestamp(&env, 3_600);
+        // Lookups of missing keys are charged by storage size in the test
+        // host, so make sure the ledger-sequence queue exists too.
+        client.register(
+            &TaskSpec {
+                clock: Clock::LedgerSequence,
+                ..far_future.clone()
+            },
+            &0,
+        );
+
+        let measure = |extra_tasks: u32| {
+            env.budget().reset_unlimited();
+            for _ in 0..extra_tasks {
+                client.register(&far_future, &0);
+            }
+
+            invocation_cost(&env, &id, || {
+                let due = client
+                    .get_due_tasks(&DueCursor::Start, &MAX_PAGE_SIZE)
+                    .tasks;
+                assert_eq!(due.len(), 1);
+                assert_eq!(due.get(0).unwrap().task_id, due_id);
+            })
+        };
+
+        let (cpu_small, mem_small) = measure(10);
+        let (cpu_large, mem_large) = measure(100);
+
+        assert_eq!(client.task_count(), 112);
+        assert_eq!(mem_small, mem_large);
+        // Only the host's storage lookups grow (logarithmically) with size
+        assert!(cpu_large < cpu_small * 3 / 2);
+    }
+
+    /// Registers `due` tasks sharing the bucket of 3_600, like daily cron
+    /// jobs, then `later` tasks sharing a far future bucket.
+    fn queue_env(due: u64, later: u64) -> (Env, Address) {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+        let target = env.register_contract(None, MockTarget);
+        env.budget().reset_unlimited();
+        for i in 0..due + later {
+            client.register(
+                &TaskSpec {
+                    start_at: (i >= due).then_some(1_000_000),
+                    ..base_spec(&env, target.clone())
+                },
+                &0,
+            );
+        }
         (env, id)
     }
 
-    fn base_config(env: &Env, target: Address) -> TaskConfig {
-        TaskConfig {
-            creator: Address::generate(env),
-            target,
+    /// Budget spent by `f`, less that of a trivial view. The test host
+    /// charges every invocation for the size of the whole contract storage.
+    fn invocation_cost(env: &Env, id: &Address, f: impl Fn()) -> (u64, u64) {
+        let client = SoroTaskContractClient::new(env, id);
+        env.budget().reset_default();
+        client.task_count();
+        let base_cpu = env.budget().cpu_instruction_cost();
+        let base_mem = env.budget().memory_bytes_cost();
+        env.budget().reset_default();
+        f();
+        (
+            env.budget().cpu_instruction_cost() - base_cpu,
+            env.budget().memory_bytes_cost() - base_mem,
+        )
+    }
+
+    #[test]
+    fn test_due_queue_writes_independent_of_colocated_tasks() {
+        let (env, id) = queue_env(3 * DUE_PAGE_SIZE, 0);
+        let client = SoroTaskContractClient::new(&env, &id);
+        let target = client.get_task(&1).unwrap().target;
+        let lone_spec = |interval: u64| TaskSpec {
+            schedule: Schedule::Interval(interval),
+            ..base_spec(&env, target.clone())
+        };
+        let lone_id = client.register(&lone_spec(4_200), &0);
+
+        // Joining or leaving the crowded bucket costs about as much as an
+        // empty one
+        let crowded = invocation_cost(&env, &id, || {
+            client.register(&lone_spec(3_600), &0);
+        });
+        let empty = invocation_cost(&env, &id, || {
+            client.register(&lone_spec(20_000), &0);
+        });
+        assert!(crowded.0 < empty.0 * 3 / 2 && crowded.1 < empty.1 * 3 / 2);
+
+        // No queue entry holds more than a page of task IDs
+        let bucket = 3_600 / BUCKET_SECONDS;
+        env.as_contract(&id, || {
+            let storage = env.storage().persistent();
+            let pages: Vec<u64> = storage
+                .get(&DataKey::DuePages(Clock::Timestamp, bucket))
+                .unwrap();
+            assert_eq!(pages, vec![&env, 0, 1, 2, 3]);
+            for page in pages.iter() {
+                let task_ids: Vec<u64> = storage
+                    .get(&DataKey::DueBucket(Clock::Timestamp, bucket, page))
+                    .unwrap();
+                assert!(task_ids.len() as u64 <= DUE_PAGE_SIZE);
+            }
+        });
+
+        set_timestamp(&env, 4_200);
+        let crowded = invocation_cost(&env, &id, || {
+            client.execute(&Address::generate(&env), &1);
+        });
+        let empty = invocation_cost(&env, &id, || {
+            client.execute(&Address::generate(&env), &lone_id);
+        });
+        assert!(crowded.0 < empty.0 * 3 / 2 && crowded.1 < empty.1 * 3 / 2);
+    }
+
+    #[test]
+    fn test_get_due_tasks_cost_independent_of_colocated_tasks() {
+        let measure = |due: u64, later: u64| {
+            let (env, id) = queue_env(due, later);
+            let client = SoroTaskContractClient::new(&env, &id);
+            set_timestamp(&env, 3_600);
+            invocation_cost(&env, &id, || {
+                assert_eq!(client.get_due_tasks(&DueCursor::Start, &10).tasks.len(), 10);
+            })
+        };
+
+        // The test host charges reads for the size of the whole storage, so
+        // compare against the same number of tasks queued elsewhere
+        let (cpu, mem) = measure(3 * DUE_PAGE_SIZE + 10, 0);
+        let (cpu_spread, mem_spread) = measure(DUE_PAGE_SIZE + 10, 2 * DUE_PAGE_SIZE);
+        assert!(cpu < cpu_spread * 11 / 10);
+        assert!(mem < mem_spread * 11 / 10);
+    }
+
+    #[test]
+    fn test_execute_batch_best_effort_reports_each_task() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let ok_id = client.register(&cfg, &0);
+        let failing_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let whitelisted_id = client.register(
+            &TaskSpec {
+                whitelist: vec![&env, Address::generate(&env)],
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let not_due_id = client.register(
+            &TaskSpec {
+                start_at: Some(53_600),
+                ..cfg.clone()
+            },
+            &0,
+        );
+
+        set_timestamp(&env, 12_345);
+        let outcomes = client.execute_batch(
+            &Address::generate(&env),
+            &vec![&env, ok_id, failing_id, whitelisted_id, not_due_id, 99],
+            &BatchMode::BestEffort,
+        );
+
+        assert_eq!(
+            outcomes,
+            vec![
+                &env,
+                ExecutionOutcome::Executed,
+                ExecutionOutcome::TargetFailed(PANIC_CODE),
+                ExecutionOutcome::Rejected(Error::Unauthorized as u32),
+                ExecutionOutcome::NotDue(53_600),
+                ExecutionOutcome::Rejected(Error::TaskNotFound as u32),
+            ]
+        );
+        assert_eq!(client.get_task(&ok_id).unwrap().last_run, 12_345);
+        let failed = client.get_task(&failing_id).unwrap();
+        assert_eq!((failed.run_count, failed.consecutive_failures), (0, 1));
+    }
+
+    /// Error code reported for a target that panics.
+    const PANIC_CODE: u32 = soroban_sdk::xdr::ScErrorCode::InvalidAction as u32;
+
+    #[test]
+    fn test_target_failures_are_recorded_and_auto_pause() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                max_failures: Some(3),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        // Without retries each failure gives its slot up
+        for failures in 1..=2 {
+            set_timestamp(&env, failures as u64 * 4_000);
+            assert_eq!(
+                client.execute(&keeper, &task_id),
+                ExecutionOutcome::TargetFailed(PANIC_CODE)
+            );
+            assert_eq!(
+                last_event_named(&env, "TaskFailed")
+                    .map(|v| <(Address, u32, u32)>::from_val(&env, &v)),
+                Some((keeper.clone(), PANIC_CODE, failures))
+            );
+        }
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.consecutive_failures, task.last_run), (2, 8_000));
+        assert_eq!(task.status, TaskStatus::Active);
+
+        // The third failure in a row pauses the task
+        set_timestamp(&env, 12_000);
+        client.execute(&keeper, &task_id);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Paused);
+        assert!(last_event_named(&env, "TaskAutoPaused").is_some());
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::Paused
+        );
+
+        // Fixing and resuming the task starts a fresh count
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "ping")),
+                ..empty_update()
+            },
+        );
+        client.resume_task(&task_id, &false);
+        assert_eq!(client.get_task(&task_id).unwrap().consecutive_failures, 0);
+        set_timestamp(&env, 16_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+    }
+
+    #[test]
+    fn test_success_resets_consecutive_failures() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 4_000);
+        client.execute(&keeper, &task_id);
+        set_timestamp(&env, 8_000);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().consecutive_failures, 2);
+
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "ping")),
+                ..empty_update()
+            },
+        );
+        set_timestamp(&env, 12_000);
+        client.execute(&keeper, &task_id);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.consecutive_failures, task.run_count), (0, 1));
+    }
+
+    #[test]
+    fn test_resume_with_reset_clock_drops_pending_retry() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                anchor: Anchor::Schedule,
+                max_failures: Some(1),
+                retry: RetryPolicy {
+                    max_retries: 2,
+                    base_backoff: 60,
+                    multiplier: 2,
+                },
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 4_000);
+        client.execute(&keeper, &task_id);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(
+            (task.status, task.retry_at),
+            (TaskStatus::Paused, Some(4_060))
+        );
+
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "ping")),
+                ..empty_update()
+            },
+        );
+        set_timestamp(&env, 4_010);
+        client.resume_task(&task_id, &true);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.retry_at, task.retry_count), (None, 0));
+
+        set_timestamp(&env, 4_060);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(7_610)
+        );
+        set_timestamp(&env, 7_610);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 7_610);
+    }
+
+    #[test]
+    fn test_schedule_update_drops_pending_retry() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                anchor: Anchor::Schedule,
+                retry: RetryPolicy {
+                    max_retries: 2,
+                    base_backoff: 60,
+                    multiplier: 2,
+                },
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 4_000);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().retry_at, Some(4_060));
+
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "ping")),
+                schedule: ScheduleUpdate::Set(Schedule::Interval(86_400)),
+                ..empty_update()
+            },
+        );
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.retry_at, task.retry_count), (None, 0));
+
+        set_timestamp(&env, 4_060);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(86_400)
+        );
+        set_timestamp(&env, 86_400);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 86_400);
+    }
+
+    #[test]
+    fn test_failed_runs_retry_with_backoff() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                retry: RetryPolicy {
+                    max_retries: 2,
+                    base_backoff: 60,
+                    multiplier: 3,
+                },
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 4_000);
+        client.execute(&keeper, &task_id);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(4_060)
+        );
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
+
+        set_timestamp(&env, 4_060);
+        assert_eq!(client.get_due_tasks(&DueCursor::Start, &10).tasks.len(), 1);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().retry_at, Some(4_240));
+
+        // Out of retries: back to the regular schedule
+        set_timestamp(&env, 4_240);
+        client.execute(&keeper, &task_id);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.retry_at, task.retry_count), (None, 0));
+        assert_eq!(task.last_run, 4_240);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(7_840)
+        );
+
+        // A successful retry counts as the run
+        set_timestamp(&env, 7_840);
+        client.execute(&keeper, &task_id);
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "ping")),
+                ..empty_update()
+            },
+        );
+        set_timestamp(&env, 7_900);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!((task.retry_at, task.retry_count), (None, 0));
+        assert_eq!((task.last_run, task.run_count), (7_900, 1));
+    }
+
+    #[test]
+    fn test_init_requires_admin_and_runs_once() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let admin = Address::generate(&env);
+        let token = Address::generate(&env);
+        client.init(&admin, &token);
+        assert_eq!(env.auths()[0].0, admin);
+        assert_eq!(client.get_admin(), admin);
+        assert_eq!(client.get_token(), token);
+
+        let result = client.try_init(&Address::generate(&env), &token);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::AlreadyInitialized as u32
+            )))
+        );
+        assert_eq!(client.get_admin(), admin);
+    }
+
+    #[test]
+    fn test_two_step_admin_transfer() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let admin = Address::generate(&env);
+        client.init(&admin, &Address::generate(&env));
+        assert_eq!(
+            client.try_accept_admin(),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::NoPendingAdmin as u32
+            )))
+        );
+
+        let new_admin = Address::generate(&env);
+        client.propose_admin(&new_admin);
+        assert_eq!(env.auths()[0].0, admin);
+        // Nothing changes until the proposed admin accepts
+        assert_eq!(client.get_admin(), admin);
+
+        client.accept_admin();
+        assert_eq!(env.auths()[0].0, new_admin);
+        assert_eq!(client.get_admin(), new_admin);
+        assert_eq!(
+            client.try_accept_admin(),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::NoPendingAdmin as u32
+            )))
+        );
+    }
+
+    #[test]
+    fn test_set_ttl_config_is_admin_only_and_validated() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let config = TtlConfig {
+            threshold: 1_000,
+            extend_to: 10_000,
+            rent_fee: 1,
+        };
+        assert_eq!(
+            client.try_set_ttl_config(&config),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::NotInitialized as u32
+            )))
+        );
+
+        let admin = Address::generate(&env);
+        client.init(&admin, &Address::generate(&env));
+        client.set_ttl_config(&config);
+        assert_eq!(env.auths()[0].0, admin);
+        assert_eq!(client.get_ttl_config(), config);
+
+        for invalid in [
+            TtlConfig {
+                threshold: 10_001,
+                ..config.clone()
+            },
+            TtlConfig {
+                extend_to: u32::MAX,
+                ..config.clone()
+            },
+            TtlConfig {
+                rent_fee: -1,
+                ..config.clone()
+            },
+            TtlConfig {
+                rent_fee: MAX_RENT_FEE + 1,
+                ..config.clone()
+            },
+        ] {
+            assert_eq!(
+                client.try_set_ttl_config(&invalid),
+                Err(Ok(soroban_sdk::Error::from_contract_error(
+                    Error::InvalidTtlConfig as u32
+                )))
+            );
+        }
+    }
+
+    fn write_v0_task(env: &Env, contract_id: &Address, task_id: u64, target: &Address) -> Address {
+        let creator = Address::generate(env);
+        let legacy = TaskConfigV0 {
+            creator: creator.clone(),
+            target: target.clone(),
             function: Symbol::new(env, "ping"),
             args: Vec::new(env),
             resolver: None,
             interval: 3_600,
             last_run: 0,
-            gas_balance: 1_000,
+            gas_balance: 500,
             whitelist: Vec::new(env),
-        }
+        };
+        env.as_contract(contract_id, || {
+            env.storage()
+                .persistent()
+                .set(&DataKey::Task(task_id), &legacy);
+            env.storage().persistent().set(&DataKey::Counter, &task_id);
+        });
+        creator
     }
 
-    fn set_timestamp(env: &Env, ts: u64) {
-        env.ledger().with_mut(|l| l.timestamp = ts);
+    #[test]
+    fn test_v0_task_entries_load_and_run() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let creator = write_v0_task(&env, &id, 1, &target);
+
+        let task = client.get_task(&1).unwrap();
+        assert_eq!(task.creator, creator);
+        assert_eq!(task.schedule, Schedule::Interval(3_600));
+        assert_eq!((task.gas_balance, task.reward), (500, 0));
+        assert_eq!(task.status, TaskStatus::Active);
+
+        set_timestamp(&env, 4_000);
+        let keeper = Address::generate(&env);
+        assert_eq!(client.execute(&keeper, &1), ExecutionOutcome::Executed);
+        assert_eq!(client.get_task(&1).unwrap().last_run, 4_000);
+
+        // The entry is written back in the current format
+        let stored = env.as_contract(&id, || {
+            env.storage()
+                .persistent()
+                .get::<_, StoredTask>(&DataKey::Task(1))
+        });
+        assert!(matches!(stored, Some(StoredTask::V1(_))));
+
+        // New tasks keep counting after the old ones
+        let new_id = client.register(&base_spec(&env, target), &0);
+        assert_eq!(new_id, 2);
     }
 
-    // ── Tests ─────────────────────────────────────────────────────────────────
+    #[test]
+    fn test_migrate_task_indexes_v0_entries() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let creator = write_v0_task(&env, &id, 1, &target);
+        set_timestamp(&env, 4_000);
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
+        assert!(client.tasks_by_creator(&creator, &0, &10).is_empty());
+
+        client.migrate_task(&1);
+        client.migrate_task(&1);
+        assert_eq!(
+            client.get_due_tasks(&DueCursor::Start, &10).tasks,
+            vec![
+                &env,
+                DueTask {
+                    task_id: 1,
+                    reward: 0
+                }
+            ]
+        );
+        assert_eq!(client.tasks_by_creator(&creator, &0, &10).len(), 1);
+        assert_eq!(due_buckets(&env, &id).len(), 1);
+    }
 
-    /// Registering a task stores it; get_task retrieves identical data.
     #[test]
-    fn test_register_and_get_task() {
+    fn test_unknown_task_format_is_rejected() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        env.as_contract(&id, || {
+            env.storage()
+                .persistent()
+                .set(&DataKey::Task(1), &Symbol::new(&env, "garbage"));
+        });
+        assert_eq!(
+            client.try_get_task(&1).err(),
+            Some(Ok(soroban_sdk::Error::from_contract_error(
+                Error::UnknownTaskFormat as u32
+            )))
+        );
+    }
+
+    #[test]
+    fn test_upgrade_is_admin_only_and_version_is_reported() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        assert_eq!(client.version(), CONTRACT_VERSION);
+
+        let hash = BytesN::from_array(&env, &[0; 32]);
+        assert_eq!(
+            client.try_upgrade(&hash),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::NotInitialized as u32
+            )))
+        );
+
+        // Past the admin check the host rejects the unknown Wasm hash
+        let admin = Address::generate(&env);
+        client.init(&admin, &Address::generate(&env));
+        assert!(client.try_upgrade(&hash).is_err());
+    }
+
+    fn advance_ledgers(env: &Env, ledgers: u32) {
+        env.ledger().with_mut(|l| l.sequence_number += ledgers);
+    }
+
+    fn task_ttl(env: &Env, contract_id: &Address, task_id: u64) -> u32 {
+        env.as_contract(contract_id, || {
+            env.storage().persistent().get_ttl(&DataKey::Task(task_id))
+        })
+    }
+
+    #[test]
+    fn test_register_and_bump_extend_ttl() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
 
         let target = env.register_contract(None, MockTarget);
-        let cfg = base_config(&env, target.clone());
-        let task_id = client.register(&cfg);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        assert_eq!(task_ttl(&env, &id, task_id), DEFAULT_TTL_EXTEND_TO);
 
-        let stored = client.get_task(&task_id).expect("task should exist");
-        assert_eq!(stored.target, target);
-        assert_eq!(stored.interval, 3_600);
-        assert_eq!(stored.last_run, 0, "last_run must start at 0");
+        // Entries above the threshold are left alone
+        advance_ledgers(&env, 1_000);
+        client.bump_task(&task_id);
+        assert_eq!(task_ttl(&env, &id, task_id), DEFAULT_TTL_EXTEND_TO - 1_000);
+
+        advance_ledgers(&env, DEFAULT_TTL_EXTEND_TO - DEFAULT_TTL_THRESHOLD);
+        assert!(task_ttl(&env, &id, task_id) < DEFAULT_TTL_THRESHOLD);
+        client.bump_task(&task_id);
+        assert_eq!(task_ttl(&env, &id, task_id), DEFAULT_TTL_EXTEND_TO);
+        let instance_ttl = env.as_contract(&id, || env.storage().instance().get_ttl());
+        assert_eq!(instance_ttl, DEFAULT_TTL_EXTEND_TO);
+
+        assert_eq!(
+            client.try_bump_task(&99),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskNotFound as u32
+            )))
+        );
     }
 
-    /// Querying a task id that was never registered returns None.
     #[test]
-    fn test_get_task_missing_returns_none() {
+    fn test_execute_keeps_task_alive() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
-        assert!(client.get_task(&99_u64).is_none());
+
+        let target = env.register_contract(None, MockTarget);
+        let executed_id = client.register(&base_spec(&env, target.clone()), &0);
+        let idle_id = client.register(&base_spec(&env, target.clone()), &0);
+        let keeper = Address::generate(&env);
+        // The target keeping itself alive is not our business
+        env.as_contract(&target, || {
+            env.storage()
+                .instance()
+                .extend_ttl(DEFAULT_TTL_EXTEND_TO, DEFAULT_TTL_EXTEND_TO * 2)
+        });
+
+        advance_ledgers(&env, DEFAULT_TTL_EXTEND_TO - 100);
+        set_timestamp(&env, 4_000);
+        assert_eq!(
+            client.execute(&keeper, &executed_id),
+            ExecutionOutcome::Executed
+        );
+
+        assert_eq!(task_ttl(&env, &id, executed_id), DEFAULT_TTL_EXTEND_TO);
+        assert_eq!(task_ttl(&env, &id, idle_id), 100);
+
+        // Past the original TTL the executed task is still readable, while
+        // the idle one was archived and the host refuses to read it
+        advance_ledgers(&env, 200);
+        assert!(client.get_task(&executed_id).is_some());
+        let idle = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            client.get_task(&idle_id);
+        }));
+        assert!(idle.is_err());
     }
 
-    /// A successful cross-contract call updates last_run to the ledger timestamp.
     #[test]
-    fn test_execute_invokes_target_and_updates_last_run() {
+    fn test_rent_fee_is_charged_with_reward() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
+        client.set_ttl_config(&TtlConfig {
+            threshold: DEFAULT_TTL_THRESHOLD,
+            extend_to: DEFAULT_TTL_EXTEND_TO,
+            rent_fee: 5,
+        });
 
         let target = env.register_contract(None, MockTarget);
-        let task_id = client.register(&base_config(&env, target));
+        let spec = TaskSpec {
+            reward: 10,
+            ..base_spec(&env, target)
+        };
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&spec.creator, &27);
+        let task_id = client.register(&spec, &27);
         let keeper = Address::generate(&env);
 
-        set_timestamp(&env, 12_345);
+        set_timestamp(&env, 4_000);
+        assert_eq!(
+            client.get_due_tasks(&DueCursor::Start, &10).tasks,
+            vec![
+                &env,
+                DueTask {
+                    task_id,
+                    reward: 15
+                }
+            ]
+        );
         client.execute(&keeper, &task_id);
+        let token_client = soroban_sdk::token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&keeper), 15);
+        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 12);
 
-        let updated = client.get_task(&task_id).unwrap();
+        // 12 covers the reward but not the fee
+        set_timestamp(&env, 8_000);
         assert_eq!(
-            updated.last_run, 12_345,
-            "last_run must reflect ledger timestamp after execution"
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::InsufficientBalance
         );
     }
 
-    /// Args stored in TaskConfig are forwarded correctly to the target function.
     #[test]
-    fn test_execute_forwards_args_to_target() {
+    fn test_history_keeps_latest_records() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
+        let token = setup_token(&env, &client);
 
         let target = env.register_contract(None, MockTarget);
+        let spec = TaskSpec {
+            schedule: Schedule::Interval(60),
+            reward: 10,
+            history_size: 3,
+            ..base_spec(&env, target)
+        };
+        soroban_sdk::token::StellarAssetClient::new(&env, &token).mint(&spec.creator, &1_000);
+        let task_id = client.register(&spec, &1_000);
+        let keeper = Address::generate(&env);
 
-        let mut args: Vec<Val> = Vec::new(&env);
-        args.push_back(5_i64.into_val(&env));
-        args.push_back(3_i64.into_val(&env));
+        for run in 1..=4 {
+            set_timestamp(&env, run * 100);
+            client.execute(&keeper, &task_id);
+        }
+        // Probes of a task that is not due yet are not recorded
+        client.execute(&keeper, &task_id);
 
-        let cfg = TaskConfig {
-            creator: Address::generate(&env),
-            target,
-            function: Symbol::new(&env, "add"),
-            args,
-            resolver: None,
-            interval: 60,
-            last_run: 0,
-            gas_balance: 500,
-            whitelist: Vec::new(&env),
+        let history = client.get_history(&task_id, &10);
+        assert_eq!(history.len(), 3);
+        let newest = history.get(0).unwrap();
+        assert_eq!(
+            newest,
+            ExecutionRecord {
+                timestamp: 400,
+                keeper: keeper.clone(),
+                outcome: ExecutionOutcome::Executed,
+                reward: 10,
+            }
+        );
+        assert_eq!(history.get(2).unwrap().timestamp, 200);
+        assert_eq!(client.get_history(&task_id, &1), vec![&env, newest]);
+
+        // Failures are recorded too, without a reward
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                function: Some(Symbol::new(&env, "fail")),
+                history_size: Some(2),
+                ..empty_update()
+            },
+        );
+        assert_eq!(client.get_history(&task_id, &10).len(), 2);
+        set_timestamp(&env, 500);
+        client.execute(&keeper, &task_id);
+        let newest = client.get_history(&task_id, &10).get(0).unwrap();
+        assert_eq!(newest.outcome, ExecutionOutcome::TargetFailed(PANIC_CODE));
+        assert_eq!(newest.reward, 0);
+
+        let result = client.try_update_task(
+            &task_id,
+            &TaskUpdate {
+                history_size: Some(MAX_HISTORY_SIZE + 1),
+                ..empty_update()
+            },
+        );
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::HistoryTooLarge as u32
+            )))
+        );
+    }
+
+    #[test]
+    fn test_execute_batch_all_or_nothing_reverts_on_failure() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let ok_id = client.register(&cfg, &0);
+        let paused_id = client.register(&cfg, &0);
+        client.pause_task(&paused_id);
+        let failing_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let second_id = client.register(&cfg, &0);
+
+        set_timestamp(&env, 12_345);
+        let keeper = Address::generate(&env);
+        let result = client.try_execute_batch(
+            &keeper,
+            &vec![&env, ok_id, paused_id],
+            &BatchMode::AllOrNothing,
+        );
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskPaused as u32
+            )))
+        );
+        assert_eq!(client.get_task(&ok_id).unwrap().last_run, 0);
+
+        let result = client.try_execute_batch(
+            &keeper,
+            &vec![&env, ok_id, failing_id],
+            &BatchMode::AllOrNothing,
+        );
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TargetFailed as u32
+            )))
+        );
+        assert_eq!(
+            client.get_task(&failing_id).unwrap().consecutive_failures,
+            0
+        );
+
+        let outcomes = client.execute_batch(
+            &keeper,
+            &vec![&env, ok_id, second_id],
+            &BatchMode::AllOrNothing,
+        );
+        assert_eq!(
+            outcomes,
+            vec![&env, ExecutionOutcome::Executed, ExecutionOutcome::Executed]
+        );
+        assert_eq!(client.get_task(&second_id).unwrap().last_run, 12_345);
+    }
+
+    #[test]
+    fn test_execute_batch_rejects_oversized_batch() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let mut task_ids = Vec::new(&env);
+        for task_id in 0..=MAX_BATCH_SIZE as u64 {
+            task_ids.push_back(task_id);
+        }
+        let result =
+            client.try_execute_batch(&Address::generate(&env), &task_ids, &BatchMode::BestEffort);
+        assert_eq!(
+            result,
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::BatchTooLarge as u32
+            )))
+        );
+    }
+
+    /// Returns the data of the last event whose first topic is `name`.
+    fn last_event_named(env: &Env, name: &str) -> Option<Val> {
+        let mut data = None;
+        for (_, topics, value) in env.events().all().iter() {
+            if Symbol::from_val(env, &topics.get(0).unwrap()) == Symbol::new(env, name) {
+                data = Some(value);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_execute_returns_outcome_and_emits_event() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(&base_spec(&env, target), &0);
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 100);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(3_600)
+        );
+        let data: (Address, u64) = last_event_named(&env, "TaskNotDue").unwrap().into_val(&env);
+        assert_eq!(data, (keeper.clone(), 3_600));
+
+        set_timestamp(&env, 3_600);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        let data: Address = last_event_named(&env, "TaskExecuted")
+            .unwrap()
+            .into_val(&env);
+        assert_eq!(data, keeper);
+    }
+
+    #[test]
+    fn test_execute_reports_resolver_outcomes() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let denying = env.register_contract(None, resolver_false::MockResolverFalse);
+        let failing = env.register_contract(None, resolver_panic::MockResolverPanic);
+        let denied_id = client.register(
+            &TaskSpec {
+                resolver: Some(denying),
+                ..base_spec(&env, target.clone())
+            },
+            &0,
+        );
+        let errored_id = client.register(
+            &TaskSpec {
+                resolver: Some(failing),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        set_timestamp(&env, 5_000);
+        assert_eq!(
+            client.execute(&keeper, &denied_id),
+            ExecutionOutcome::ResolverDenied
+        );
+        assert!(last_event_named(&env, "ResolverDenied").is_some());
+
+        assert_eq!(
+            client.execute(&keeper, &errored_id),
+            ExecutionOutcome::ResolverErrored
+        );
+        assert!(last_event_named(&env, "ResolverErrored").is_some());
+        assert_eq!(client.get_task(&errored_id).unwrap().last_run, 0);
+    }
+
+    #[test]
+    fn test_can_execute_reports_reason() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        let keeper = Address::generate(&env);
+
+        let ready_id = client.register(&cfg, &0);
+        let paused_id = client.register(&cfg, &0);
+        client.pause_task(&paused_id);
+        let whitelisted_id = client.register(
+            &TaskSpec {
+                whitelist: vec![&env, Address::generate(&env)],
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let underfunded_id = client.register(
+            &TaskSpec {
+                reward: 5_000,
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let denied_id = client.register(
+            &TaskSpec {
+                resolver: Some(env.register_contract(None, resolver_false::MockResolverFalse)),
+                ..cfg.clone()
+            },
+            &0,
+        );
+        let errored_id = client.register(
+            &TaskSpec {
+                resolver: Some(env.register_contract(None, resolver_panic::MockResolverPanic)),
+                ..cfg.clone()
+            },
+            &0,
+        );
+
+        set_timestamp(&env, 1_000);
+        assert_eq!(
+            client.can_execute(&keeper, &ready_id),
+            ExecutionCheck::NotDue(3_600)
+        );
+
+        set_timestamp(&env, 3_600);
+        assert_eq!(
+            client.can_execute(&keeper, &ready_id),
+            ExecutionCheck::Ready
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &paused_id),
+            ExecutionCheck::Paused
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &whitelisted_id),
+            ExecutionCheck::Unauthorized
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &underfunded_id),
+            ExecutionCheck::InsufficientBalance
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &denied_id),
+            ExecutionCheck::ResolverDenied
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &errored_id),
+            ExecutionCheck::ResolverErrored
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &99),
+            ExecutionCheck::TaskNotFound
+        );
+
+        // The view does not change any state
+        assert_eq!(client.get_task(&ready_id).unwrap().last_run, 0);
+    }
+
+    // 2024-01-01T00:00:00Z, a Monday
+    const JAN_1_2024: u64 = 1_704_067_200;
+
+    #[test]
+    fn test_cron_schedule_runs_on_calendar_slots() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
+
+        let target = env.register_contract(None, MockTarget);
+        let cfg = TaskSpec {
+            schedule: Schedule::Cron(String::from_str(&env, "0 0 1 * *")),
+            ..base_spec(&env, target)
         };
+        set_timestamp(&env, JAN_1_2024);
+        let task_id = client.register(&cfg, &0);
+        let keeper = Address::generate(&env);
+
+        let feb_1 = JAN_1_2024 + 31 * 86_400;
+        set_timestamp(&env, feb_1 - 1);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(feb_1)
+        );
+
+        // A late run does not shift the next slot
+        set_timestamp(&env, feb_1 + 600);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        let mar_1 = feb_1 + 29 * 86_400;
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(mar_1)
+        );
+    }
+
+    #[test]
+    fn test_timestamp_schedule_runs_each_listed_time() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let task_id = client.register(&cfg);
+        let target = env.register_contract(None, MockTarget);
+        let cfg = TaskSpec {
+            schedule: Schedule::Timestamps(vec![&env, 1_000, 5_000]),
+            ..base_spec(&env, target)
+        };
+        let task_id = client.register(&cfg, &0);
         let keeper = Address::generate(&env);
-        set_timestamp(&env, 99_999);
-        client.execute(&keeper, &task_id);
 
-        assert_eq!(client.get_task(&task_id).unwrap().last_run, 99_999);
+        set_timestamp(&env, 1_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(5_000)
+        );
+
+        set_timestamp(&env, 5_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        // The schedule ran out, so the task completed with its last run
+        assert_eq!(
+            client.get_task(&task_id).unwrap().status,
+            TaskStatus::Completed
+        );
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
     }
 
-    /// When a resolver returns true the target is invoked and last_run updated.
     #[test]
-    fn test_execute_with_resolver_true_proceeds() {
+    fn test_register_rejects_invalid_schedules() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
 
         let target = env.register_contract(None, MockTarget);
-        let resolver = env.register_contract(None, resolver_true::MockResolverTrue);
+        let cfg = base_spec(&env, target);
+        let invalid = [
+            Schedule::Cron(String::from_str(&env, "61 * * * *")),
+            Schedule::Cron(String::from_str(&env, "* * *")),
+            Schedule::Cron(String::from_str(&env, "0 0 30 2 *")),
+            Schedule::Timestamps(Vec::new(&env)),
+            Schedule::Timestamps(vec![&env, 5_000, 1_000]),
+        ];
+        for schedule in invalid {
+            let result = client.try_register(
+                &TaskSpec {
+                    schedule,
+                    ..cfg.clone()
+                },
+                &0,
+            );
+            assert_eq!(
+                result,
+                Err(Ok(soroban_sdk::Error::from_contract_error(
+                    Error::InvalidSchedule as u32
+                )))
+            );
+        }
 
-        let cfg = TaskConfig {
-            resolver: Some(resolver),
-            ..base_config(&env, target)
+        // Run times that have all passed can never fire
+        set_timestamp(&env, 5_000);
+        let past = TaskSpec {
+            schedule: Schedule::Timestamps(vec![&env, 1_000, 5_000]),
+            ..cfg.clone()
         };
+        assert_eq!(
+            client.try_register(&past, &0),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::InvalidSchedule as u32
+            )))
+        );
+        let task_id = client.register(&cfg, &0);
+        assert_eq!(
+            client.try_update_task(
+                &task_id,
+                &TaskUpdate {
+                    schedule: ScheduleUpdate::Set(past.schedule),
+                    ..empty_update()
+                },
+            ),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::InvalidSchedule as u32
+            )))
+        );
+        let task_id = client.register(
+            &TaskSpec {
+                schedule: Schedule::Timestamps(vec![&env, 1_000, 5_001]),
+                ..cfg
+            },
+            &0,
+        );
 
-        let task_id = client.register(&cfg);
+        // A task waiting for its last listed time can still be updated
+        set_timestamp(&env, 5_001);
         let keeper = Address::generate(&env);
-        set_timestamp(&env, 55_000);
-        client.execute(&keeper, &task_id);
-
-        assert_eq!(
-            client.get_task(&task_id).unwrap().last_run,
-            55_000,
-            "resolver approved — last_run must be updated"
+        assert_eq!(client.can_execute(&keeper, &task_id), ExecutionCheck::Ready);
+        client.update_task(
+            &task_id,
+            &TaskUpdate {
+                whitelist: Some(vec![&env, keeper.clone()]),
+                ..empty_update()
+            },
         );
+        assert_eq!(client.can_execute(&keeper, &task_id), ExecutionCheck::Ready);
     }
 
-    /// When a resolver returns false the target is NOT invoked and last_run is
-    /// left unchanged.
     #[test]
-    fn test_execute_with_resolver_false_skips_invocation() {
+    fn test_task_waits_for_start_at() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
 
         let target = env.register_contract(None, MockTarget);
-        let resolver = env.register_contract(None, resolver_false::MockResolverFalse);
-
-        let cfg = TaskConfig {
-            resolver: Some(resolver),
-            ..base_config(&env, target)
-        };
-
-        let task_id = client.register(&cfg);
+        let task_id = client.register(
+            &TaskSpec {
+                start_at: Some(10_000),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
         let keeper = Address::generate(&env);
-        set_timestamp(&env, 77_777);
-        client.execute(&keeper, &task_id);
 
+        set_timestamp(&env, 5_000);
         assert_eq!(
-            client.get_task(&task_id).unwrap().last_run,
-            0,
-            "resolver denied — last_run must not change"
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(10_000)
+        );
+
+        set_timestamp(&env, 10_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
         );
+        assert_eq!(client.get_task(&task_id).unwrap().run_count, 1);
     }
 
-    /// Calling execute multiple times updates last_run on every successful run.
     #[test]
-    fn test_execute_repeated_calls_update_timestamp_each_time() {
+    fn test_task_completes_after_max_runs() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
+        let token_address = setup_token(&env, &client);
+        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
+        let token_admin_client = soroban_sdk::token::StellarAssetClient::new(&env, &token_address);
 
         let target = env.register_contract(None, MockTarget);
-        let mut cfg = base_config(&env, target);
-        cfg.interval = 1; // Small interval to allow repeated execution
-        let task_id = client.register(&cfg);
+        let cfg = TaskSpec {
+            schedule: Schedule::Interval(100),
+            reward: 10,
+            max_runs: Some(2),
+            cancel_notice: 600,
+            ..base_spec(&env, target)
+        };
+        let creator = cfg.creator.clone();
+        let task_id = client.register(&cfg, &0);
+        token_admin_client.mint(&creator, &100);
+        client.deposit_gas(&task_id, &creator, &100);
         let keeper = Address::generate(&env);
 
-        set_timestamp(&env, 1_000);
+        set_timestamp(&env, 100);
         client.execute(&keeper, &task_id);
-        assert_eq!(client.get_task(&task_id).unwrap().last_run, 1_000);
-
-        set_timestamp(&env, 2_000);
+        set_timestamp(&env, 200);
         client.execute(&keeper, &task_id);
+
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(task.run_count, 2);
+        assert_eq!(task.status, TaskStatus::Completed);
+        let data: (u32, i128) = last_event_named(&env, "TaskCompleted")
+            .unwrap()
+            .into_val(&env);
+        assert_eq!(data, (2, 80));
+
+        set_timestamp(&env, 300);
         assert_eq!(
-            client.get_task(&task_id).unwrap().last_run,
-            2_000,
-            "last_run must advance on each execution"
+            client.try_execute(&keeper, &task_id),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskCompleted as u32
+            )))
         );
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
+
+        // The leftover balance is refunded without waiting for the notice
+        client.cancel_task(&task_id);
+        assert_eq!(token_client.balance(&creator), 80);
     }
 
     #[test]
-    fn test_register_and_get() {
-        let env = Env::default();
-        env.mock_all_auths();
+    fn test_task_completes_after_end_at() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let contract_id = env.register_contract(None, SoroTaskContract);
-        let client = SoroTaskContractClient::new(&env, &contract_id);
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                schedule: Schedule::Interval(100),
+                end_at: Some(250),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
 
-        let creator = Address::generate(&env);
-        let target = Address::generate(&env);
+        set_timestamp(&env, 100);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
 
-        let config = TaskConfig {
-            creator: creator.clone(),
-            target: target.clone(),
-            function: Symbol::new(&env, "hello"),
-            args: vec![&env, 0i128.into_val(&env)],
-            resolver: None,
-            interval: 3600,
-            last_run: 0,
-            gas_balance: 1000,
-            whitelist: Vec::new(&env),
-        };
+        // The slot at 200 is still inside the lifetime, but nobody ran it
+        set_timestamp(&env, 260);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::Completed
+        );
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Completed
+        );
 
-        let task_id = client.register(&config);
-        assert_eq!(task_id, 1);
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.run_count, 1);
+        assert_eq!(
+            client.try_resume_task(&task_id, &false),
+            Err(Ok(soroban_sdk::Error::from_contract_error(
+                Error::TaskCompleted as u32
+            )))
+        );
+    }
 
-        let retrieved_config = client.get_task(&task_id).unwrap();
-        assert_eq!(retrieved_config.creator, config.creator);
-        assert_eq!(retrieved_config.target, config.target);
-        assert_eq!(retrieved_config.function, config.function);
-        assert_eq!(retrieved_config.interval, config.interval);
-        assert_eq!(retrieved_config.gas_balance, config.gas_balance);
+    #[test]
+    fn test_register_rejects_invalid_lifetime() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        // Check event
-        let events = env.events().all();
-        let last_event = events.last().unwrap();
+        let target = env.register_contract(None, MockTarget);
+        let cfg = base_spec(&env, target);
+        for invalid in [
+            TaskSpec {
+                start_at: Some(500),
+                end_at: Some(400),
+                ..cfg.clone()
+            },
+            TaskSpec {
+                max_runs: Some(0),
+                ..cfg.clone()
+            },
+        ] {
+            assert_eq!(
+                client.try_register(&invalid, &0),
+                Err(Ok(soroban_sdk::Error::from_contract_error(
+                    Error::InvalidSchedule as u32
+                )))
+            );
+        }
+    }
 
-        assert_eq!(last_event.0, contract_id);
+    #[test]
+    fn test_schedule_anchor_does_not_drift() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let topics = last_event.1.clone();
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                anchor: Anchor::Schedule,
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
+
+        // Ten minutes late for the 3_600 slot
+        set_timestamp(&env, 4_200);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 3_600);
         assert_eq!(
-            Symbol::from_val(&env, &topics.get(0).unwrap()),
-            Symbol::new(&env, "TaskRegistered")
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(7_200)
         );
-        assert_eq!(u64::from_val(&env, &topics.get(1).unwrap()), 1u64);
 
-        let data: Address = last_event.2.clone().into_val(&env);
-        assert_eq!(data, creator);
+        // Several slots missed: only the latest one runs
+        set_timestamp(&env, 18_100);
+        client.execute(&keeper, &task_id);
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 18_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(21_600)
+        );
     }
 
     #[test]
-    fn test_sequential_ids() {
-        let env = Env::default();
-        env.mock_all_auths();
+    fn test_schedule_anchor_backfills_missed_slots() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let contract_id = env.register_contract(None, SoroTaskContract);
-        let client = SoroTaskContractClient::new(&env, &contract_id);
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                anchor: Anchor::Schedule,
+                catch_up: CatchUp::Backfill(2),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
 
-        let creator = Address::generate(&env);
-        let target = Address::generate(&env);
+        // Slots 3_600 .. 21_600 have passed; only the last two missed ones
+        // are backfilled on top of the current one.
+        set_timestamp(&env, 21_610);
+        let mut runs = Vec::<u64>::new(&env);
+        while client.execute(&keeper, &task_id) == ExecutionOutcome::Executed {
+            runs.push_back(client.get_task(&task_id).unwrap().last_run);
+        }
+        assert_eq!(runs, vec![&env, 14_400, 18_000, 21_600]);
+    }
 
-        let config = TaskConfig {
-            creator: creator.clone(),
-            target: target.clone(),
-            function: Symbol::new(&env, "hello"),
-            args: vec![&env],
-            resolver: None,
-            interval: 3600,
-            last_run: 0,
-            gas_balance: 1000,
-            whitelist: Vec::new(&env),
-        };
+    #[test]
+    fn test_schedule_anchor_backfills_cron_slots() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let id1 = client.register(&config);
-        let id2 = client.register(&config);
+        let target = env.register_contract(None, MockTarget);
+        set_timestamp(&env, JAN_1_2024);
+        let task_id = client.register(
+            &TaskSpec {
+                schedule: Schedule::Cron(String::from_str(&env, "0 * * * *")),
+                anchor: Anchor::Schedule,
+                catch_up: CatchUp::Backfill(1),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
 
-        assert_eq!(id1, 1);
-        assert_eq!(id2, 2);
+        set_timestamp(&env, JAN_1_2024 + 4 * 3_600 + 30);
+        let mut runs = Vec::<u64>::new(&env);
+        while client.execute(&keeper, &task_id) == ExecutionOutcome::Executed {
+            runs.push_back(client.get_task(&task_id).unwrap().last_run - JAN_1_2024);
+        }
+        assert_eq!(runs, vec![&env, 3 * 3_600, 4 * 3_600]);
     }
 
     #[test]
-    fn test_register_invalid_interval() {
-        let env = Env::default();
-        env.mock_all_auths();
-
-        let contract_id = env.register_contract(None, SoroTaskContract);
-        let client = SoroTaskContractClient::new(&env, &contract_id);
+    fn test_execution_window_skips_late_slots() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let creator = Address::generate(&env);
-        let target = Address::generate(&env);
+        // The target panics, so any invocation would fail the call
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                function: Symbol::new(&env, "fail"),
+                execution_window: Some(300),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
 
-        let config = TaskConfig {
-            creator: creator.clone(),
-            target: target.clone(),
-            function: Symbol::new(&env, "hello"),
-            args: vec![&env],
-            resolver: None,
-            interval: 0, // Invalid
-            last_run: 0,
-            gas_balance: 1000,
-            whitelist: Vec::new(&env),
-        };
+        set_timestamp(&env, 3_901);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::SlotMissed(3_600)
+        );
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::SlotMissed(1)
+        );
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(
+            (task.last_run, task.missed_slots, task.run_count),
+            (3_600, 1, 0)
+        );
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(7_200)
+        );
 
-        let result = client.try_register(&config);
-        assert_eq!(result, Err(Ok(soroban_sdk::Error::from_contract_error(1))));
+        // Three more windows close while no keeper shows up; the slot at
+        // 18_000 is still open.
+        set_timestamp(&env, 18_200);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::SlotMissed(3)
+        );
+        assert_eq!(
+            last_event_named(&env, "SlotMissed").map(|v| <(u32, u32)>::from_val(&env, &v)),
+            Some((3, 4))
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 14_400);
+        assert_eq!(client.can_execute(&keeper, &task_id), ExecutionCheck::Ready);
     }
 
     #[test]
-    fn test_execute_honors_interval() {
-        let env = Env::default();
-        env.mock_all_auths();
-
-        let contract_id = env.register_contract(None, SoroTaskContract);
-        let client = SoroTaskContractClient::new(&env, &contract_id);
-
-        let creator = Address::generate(&env);
-        let dummy_id = env.register_contract(None, DummyContract);
-        let target = dummy_id.clone();
-
-        let config = TaskConfig {
-            creator: creator.clone(),
-            target: target.clone(),
-            function: Symbol::new(&env, "hello"),
-            args: Vec::new(&env),
-            resolver: None,
-            interval: 100,
-            last_run: 0,
-            gas_balance: 1000,
-            whitelist: Vec::new(&env),
-        };
+    fn test_execution_window_on_timestamp_schedule() {
+        let (env, id) = setup();
+        let client = SoroTaskContractClient::new(&env, &id);
 
-        let task_id = client.register(&config);
+        let target = env.register_contract(None, MockTarget);
+        let task_id = client.register(
+            &TaskSpec {
+                schedule: Schedule::Timestamps(vec![&env, 1_000, 2_000, 3_000]),
+                execution_window: Some(60),
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
         let keeper = Address::generate(&env);
 
-        // First execution (ledger 50, last_run 0, interval 100)
-        // 50 < 0 + 100 -> returns early
-        env.ledger().set_timestamp(50);
-        client.execute(&keeper, &task_id);
-        assert_eq!(client.get_task(&task_id).unwrap().last_run, 0);
-
-        env.ledger().set_timestamp(150);
-        client.execute(&keeper, &task_id);
-        assert_eq!(client.get_task(&task_id).unwrap().last_run, 150);
+        set_timestamp(&env, 2_030);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::SlotMissed(1)
+        );
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
 
-        // Next execution too soon
-        env.ledger().set_timestamp(200);
-        client.execute(&keeper, &task_id);
-        assert_eq!(client.get_task(&task_id).unwrap().last_run, 150);
+        // The last slot is missed too, which ends the schedule
+        set_timestamp(&env, 3_100);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::SlotMissed(1)
+        );
+        let task = client.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!((task.run_count, task.missed_slots), (1, 2));
     }
 
     #[test]
-    fn test_gas_management_lifecycle() {
+    fn test_ledger_sequence_clock() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
 
-        let token_admin = Address::generate(&env);
-        let token_id = env.register_stellar_asset_contract_v2(token_admin.clone());
-        let token_address = token_id.address();
-        let token_client = soroban_sdk::token::Client::new(&env, &token_address);
-        let token_admin_client = soroban_sdk::token::StellarAssetClient::new(&env, &token_address);
-
-        client.init(&token_address);
-
         let target = env.register_contract(None, MockTarget);
-        let mut cfg = base_config(&env, target);
-        cfg.gas_balance = 0;
-        let creator = cfg.creator.clone();
-        let task_id = client.register(&cfg);
-
-        // Mint tokens to creator
-        token_admin_client.mint(&creator, &5000);
-        assert_eq!(token_client.balance(&creator), 5000);
+        env.ledger().with_mut(|l| l.sequence_number = 1_000);
+        let task_id = client.register(
+            &TaskSpec {
+                schedule: Schedule::Interval(120),
+                clock: Clock::LedgerSequence,
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
+        let keeper = Address::generate(&env);
 
-        // Deposit gas
-        client.deposit_gas(&task_id, &creator, &2000);
-        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 2000);
-        assert_eq!(token_client.balance(&creator), 3000);
-        assert_eq!(token_client.balance(&id), 2000);
+        // Timestamps are ignored, however far they move
+        set_timestamp(&env, 1_000_000);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::NotDue(1_120)
+        );
+        assert!(client
+            .get_due_tasks(&DueCursor::Start, &10)
+            .tasks
+            .is_empty());
 
-        // Withdraw gas
-        client.withdraw_gas(&task_id, &500);
-        assert_eq!(client.get_task(&task_id).unwrap().gas_balance, 1500);
-        assert_eq!(token_client.balance(&creator), 3500);
+        env.ledger().with_mut(|l| l.sequence_number = 1_125);
+        assert_eq!(client.get_due_tasks(&DueCursor::Start, &10).tasks.len(), 1);
+        assert_eq!(
+            client.execute(&keeper, &task_id),
+            ExecutionOutcome::Executed
+        );
+        assert_eq!(client.get_task(&task_id).unwrap().last_run, 1_125);
+        assert_eq!(
+            client.can_execute(&keeper, &task_id),
+            ExecutionCheck::NotDue(1_245)
+        );
     }
 
     #[test]
-    fn test_withdraw_gas_insufficient_balance() {
+    fn test_register_rejects_cron_on_ledger_sequence_clock() {
         let (env, id) = setup();
         let client = SoroTaskContractClient::new(&env, &id);
 
-        let token_id = env.register_stellar_asset_contract_v2(Address::generate(&env));
-        let token_address = token_id.address();
-        client.init(&token_address);
-
         let target = env.register_contract(None, MockTarget);
-        let mut cfg = base_config(&env, target);
-        cfg.gas_balance = 1000;
-        let task_id = client.register(&cfg);
-
-        let result = client.try_withdraw_gas(&task_id, &1500);
+        let result = client.try_register(
+            &TaskSpec {
+                schedule: Schedule::Cron(String::from_str(&env, "0 * * * *")),
+                clock: Clock::LedgerSequence,
+                ..base_spec(&env, target)
+            },
+            &0,
+        );
         assert_eq!(
             result,
             Err(Ok(soroban_sdk::Error::from_contract_error(
-                Error::InsufficientBalance as u32
+                Error::InvalidSchedule as u32
             )))
         );
     }
@@ -721,9 +5614,9 @@ mod tests {
         let allowed_keeper = Address::generate(&env);
         let unauthorized_keeper = Address::generate(&env);
 
-        let mut config = base_config(&env, target);
+        let mut config = base_spec(&env, target);
         config.whitelist = vec![&env, allowed_keeper.clone()];
-        let task_id = client.register(&config);
+        let task_id = client.register(&config, &0);
 
         set_timestamp(&env, 12_345);
         let result = client.try_execute(&unauthorized_keeper, &task_id);
@@ -738,9 +5631,9 @@ mod tests {
         let target = env.register_contract(None, MockTarget);
         let allowed_keeper = Address::generate(&env);
 
-        let mut config = base_config(&env, target);
+        let mut config = base_spec(&env, target);
         config.whitelist = vec![&env, allowed_keeper.clone()];
-        let task_id = client.register(&config);
+        let task_id = client.register(&config, &0);
 
         set_timestamp(&env, 12_345);
         client.execute(&allowed_keeper, &task_id);